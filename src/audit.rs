@@ -0,0 +1,457 @@
+use crate::config::DEFAULT_IGNORED_PREFIXES;
+use crate::inputs::SourceLocation;
+use crate::patterns::NamePattern;
+use crate::to_css;
+use lightningcss::declaration::DeclarationBlock;
+use lightningcss::printer::PrinterOptions;
+use lightningcss::properties::custom::{
+    CustomPropertyName, TokenList, TokenOrValue, UnresolvedColor,
+};
+use lightningcss::properties::Property;
+use lightningcss::rules::keyframes::{KeyframesName, KeyframesRule};
+use lightningcss::rules::style::StyleRule;
+use lightningcss::rules::{CssRule, Location};
+use lightningcss::targets::Targets;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A place where a custom property is read with `var()`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(skip)]
+    pub property: String,
+    pub selector: String,
+    /// The at-rules the rule is nested in, outermost first.
+    pub context: Vec<String>,
+    pub location: SourceLocation,
+    /// Whether the `var()` has a fallback value for when the custom property isn't defined.
+    #[serde(skip)]
+    pub has_fallback: bool,
+}
+
+/// A place where a custom property is given a value.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Definition {
+    #[serde(skip)]
+    pub property: String,
+    pub value: String,
+    pub selector: String,
+    /// The at-rules the rule is nested in, outermost first.
+    pub context: Vec<String>,
+    pub location: SourceLocation,
+    /// The custom properties the value reads with `var()`.
+    #[serde(skip)]
+    pub references: Vec<String>,
+}
+
+/// A declaration of a regular property, kept so its value can be compared with the values of
+/// custom properties.
+#[derive(Clone)]
+pub struct Declaration {
+    pub property: String,
+    pub value: String,
+    /// Every selector of the rule, separated by commas.
+    pub selector: String,
+    pub context: Vec<String>,
+    pub location: SourceLocation,
+}
+
+/// The custom properties a selector reads and defines, across every rule it appears in.
+#[derive(Serialize, Deserialize)]
+pub struct SelectorUsage {
+    pub selector: String,
+    /// The at-rules the rules are nested in, outermost first.
+    pub context: Vec<String>,
+    /// Custom properties read with `var()`.
+    pub reads: BTreeSet<String>,
+    /// Custom properties given a value.
+    pub defines: BTreeSet<String>,
+    pub locations: BTreeSet<SourceLocation>,
+}
+
+/// Joins a selector to the at-rules it's nested in, each on its own line, in the form it's
+/// listed in the terminal and HTML reports.
+pub fn label(context: &[String], selector: &str) -> String {
+    let mut label = String::new();
+    for at_rule in context {
+        label.push_str(at_rule);
+        label.push_str("\n    ");
+    }
+    label.push_str(selector);
+    label
+}
+
+/// Joins a selector to the at-rules it's nested in on one line, like `@media print .btn`, in
+/// the form it's written in messages.
+pub fn inline_label(context: &[String], selector: &str) -> String {
+    let mut label = context.join(" ");
+    if !label.is_empty() {
+        label.push(' ');
+    }
+    label.push_str(selector);
+    label
+}
+
+/// Every custom property usage and definition found in the audited stylesheets.
+#[derive(Default)]
+pub struct Audit {
+    pub usages: Vec<Usage>,
+    pub definitions: Vec<Definition>,
+    pub declarations: Vec<Declaration>,
+    pub options: AuditOptions,
+}
+
+/// What the audit leaves out, and how it prints values.
+pub struct AuditOptions {
+    /// Custom properties whose names start with any of these are left out of the audit.
+    pub ignored_prefixes: Vec<String>,
+    /// When any are given, only custom properties whose names match one of these are audited.
+    pub include: Vec<NamePattern>,
+    /// Custom properties whose names match any of these are left out of the audit.
+    pub exclude: Vec<NamePattern>,
+    /// The browsers values are printed for, so colors are written in a form they support.
+    pub targets: Targets,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        AuditOptions {
+            ignored_prefixes: DEFAULT_IGNORED_PREFIXES
+                .iter()
+                .map(|prefix| prefix.to_string())
+                .collect(),
+            include: vec![],
+            exclude: vec![],
+            targets: Targets::default(),
+        }
+    }
+}
+
+impl AuditOptions {
+    fn is_ignored(&self, name: &str) -> bool {
+        self.ignored_prefixes
+            .iter()
+            .any(|prefix| name.starts_with(prefix.as_str()))
+            || self.exclude.iter().any(|pattern| pattern.is_match(name))
+            || (!self.include.is_empty()
+                && !self.include.iter().any(|pattern| pattern.is_match(name)))
+    }
+}
+
+impl Audit {
+    /// Records the custom properties used and defined by `rule`, returning the names of the ones
+    /// it uses.
+    pub fn record_rule(&mut self, rule: &AuditedRule, location: &SourceLocation) -> Vec<String> {
+        let mut used: Vec<String> = vec![];
+        let selectors = rule.selectors_as_strings();
+        for declarations in rule.declarations() {
+            let DeclarationBlockProperties {
+                usages,
+                definitions,
+                values,
+            } = handle_declarations(declarations, &self.options);
+            for selector in &selectors {
+                for reference in &usages {
+                    self.usages.push(Usage {
+                        property: reference.name.clone(),
+                        selector: selector.clone(),
+                        context: rule.context().to_vec(),
+                        location: location.clone(),
+                        has_fallback: reference.has_fallback,
+                    });
+                }
+                for definition in &definitions {
+                    self.definitions.push(Definition {
+                        property: definition.name.clone(),
+                        value: definition.value.clone(),
+                        selector: selector.clone(),
+                        context: rule.context().to_vec(),
+                        location: location.clone(),
+                        references: definition.references.clone(),
+                    });
+                }
+            }
+            for reference in usages {
+                if !used.contains(&reference.name) {
+                    used.push(reference.name);
+                }
+            }
+            for (property, value) in values {
+                self.declarations.push(Declaration {
+                    property,
+                    value,
+                    selector: selectors.join(", "),
+                    context: rule.context().to_vec(),
+                    location: location.clone(),
+                });
+            }
+        }
+        used
+    }
+
+    /// Inverts the audit into the custom properties each selector reads and defines, sorted by
+    /// context and then selector.
+    pub fn selectors(&self) -> Vec<SelectorUsage> {
+        let mut selectors: BTreeMap<(&[String], &str), SelectorUsage> = BTreeMap::new();
+        let new_selector = |context: &[String], selector: &str| SelectorUsage {
+            selector: selector.to_string(),
+            context: context.to_vec(),
+            reads: BTreeSet::new(),
+            defines: BTreeSet::new(),
+            locations: BTreeSet::new(),
+        };
+        for usage in &self.usages {
+            let selector = selectors
+                .entry((&usage.context, &usage.selector))
+                .or_insert_with(|| new_selector(&usage.context, &usage.selector));
+            selector.reads.insert(usage.property.clone());
+            selector.locations.insert(usage.location.clone());
+        }
+        for definition in &self.definitions {
+            let selector = selectors
+                .entry((&definition.context, &definition.selector))
+                .or_insert_with(|| new_selector(&definition.context, &definition.selector));
+            selector.defines.insert(definition.property.clone());
+            selector.locations.insert(definition.location.clone());
+        }
+        selectors.into_values().collect()
+    }
+}
+
+/// A rule whose declarations are audited.
+pub enum AuditedRule<'a, 'i> {
+    Keyframes {
+        name: String,
+        rule: &'a KeyframesRule<'i>,
+    },
+    Style {
+        /// The at-rules the style rule is nested in, outermost first.
+        context: Vec<String>,
+        rule: &'a StyleRule<'i>,
+    },
+}
+
+impl<'a, 'i> AuditedRule<'a, 'i> {
+    pub fn selectors_as_strings(&self) -> Vec<String> {
+        match self {
+            AuditedRule::Keyframes { name, .. } => vec![name.to_string()],
+            AuditedRule::Style { rule, .. } => rule.selectors.0.iter().map(to_css).collect(),
+        }
+    }
+
+    pub fn context(&self) -> &[String] {
+        match self {
+            AuditedRule::Keyframes { .. } => &[],
+            AuditedRule::Style { context, .. } => context,
+        }
+    }
+
+    pub fn loc(&self) -> Location {
+        match self {
+            AuditedRule::Keyframes { rule, .. } => rule.loc,
+            AuditedRule::Style { rule, .. } => rule.loc,
+        }
+    }
+
+    fn declarations(&self) -> Vec<&'a DeclarationBlock<'i>> {
+        match self {
+            AuditedRule::Keyframes { rule, .. } => rule
+                .keyframes
+                .iter()
+                .map(|keyframe| &keyframe.declarations)
+                .collect(),
+            AuditedRule::Style { rule, .. } => vec![&rule.declarations],
+        }
+    }
+}
+
+/// Collects the rules that can contain custom properties, including style rules nested in
+/// @media, @supports, @container and @layer blocks.
+pub fn audited_rules<'a, 'i>(rules: &'a [CssRule<'i>]) -> Vec<AuditedRule<'a, 'i>> {
+    let mut audited: Vec<AuditedRule> = vec![];
+    collect_rules(rules, &[], &mut audited);
+    audited
+}
+
+fn collect_rules<'a, 'i>(
+    rules: &'a [CssRule<'i>],
+    context: &[String],
+    audited: &mut Vec<AuditedRule<'a, 'i>>,
+) {
+    let nested = |at_rule: String| {
+        let mut context = context.to_vec();
+        context.push(at_rule);
+        context
+    };
+
+    for rule in rules {
+        match rule {
+            CssRule::Keyframes(rule) => {
+                let name = match &rule.name {
+                    KeyframesName::Ident(ident) => format!("@keyframes {}", ident),
+                    KeyframesName::Custom(string) => format!("@keyframes {}", string),
+                };
+                audited.push(AuditedRule::Keyframes { name, rule });
+            }
+            CssRule::CustomMedia(media) => {
+                eprintln!("@custom-media is not supported: {:?}", media);
+            }
+            CssRule::Media(media) => {
+                let context = nested(format!("@media {}", to_css(&media.query)));
+                collect_rules(&media.rules.0, &context, audited);
+            }
+            CssRule::Supports(supports) => {
+                let context = nested(format!("@supports {}", to_css(&supports.condition)));
+                collect_rules(&supports.rules.0, &context, audited);
+            }
+            CssRule::Container(container) => {
+                let context = nested(format!("@container {}", to_css(&container.condition)));
+                collect_rules(&container.rules.0, &context, audited);
+            }
+            CssRule::LayerBlock(layer_block) => {
+                let context = nested(match &layer_block.name {
+                    Some(name) => format!("@layer {}", to_css(name)),
+                    None => "@layer".to_string(),
+                });
+                collect_rules(&layer_block.rules.0, &context, audited);
+            }
+            CssRule::Style(style) => {
+                audited.push(AuditedRule::Style {
+                    context: context.to_vec(),
+                    rule: style,
+                });
+            }
+            CssRule::Scope(scope) => {
+                eprintln!("@scope is not supported: {:?}", scope);
+            }
+            CssRule::Nesting(nesting) => {
+                eprintln!("nesting is not supported: {:?}", nesting);
+            }
+            CssRule::StartingStyle(starting_style) => {
+                eprintln!("@starting-style is not supported: {:?}", starting_style);
+            }
+            CssRule::Property(property) => {
+                eprintln!("@property is not supported: {:?}", property);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The names and values found in a declaration block.
+struct DeclarationBlockProperties {
+    /// Custom properties read with `var()`.
+    usages: Vec<VarReference>,
+    /// Custom properties given a value.
+    definitions: Vec<CustomValue>,
+    /// Every other property, with its value.
+    values: Vec<(String, String)>,
+}
+
+/// A custom property read with `var()`.
+struct VarReference {
+    name: String,
+    has_fallback: bool,
+}
+
+/// A custom property given a value in a declaration block.
+struct CustomValue {
+    name: String,
+    value: String,
+    /// The custom properties the value reads with `var()`.
+    references: Vec<String>,
+}
+
+// find the custom properties read and defined by a declaration block, along with the value of
+// every other declaration
+fn handle_declarations(
+    declarations: &DeclarationBlock,
+    options: &AuditOptions,
+) -> DeclarationBlockProperties {
+    let printer_options = || PrinterOptions {
+        targets: options.targets,
+        ..PrinterOptions::default()
+    };
+    let mut usages: Vec<VarReference> = vec![];
+    let mut definitions: Vec<CustomValue> = vec![];
+    let mut values: Vec<(String, String)> = vec![];
+
+    for declaration in declarations
+        .declarations
+        .iter()
+        .chain(declarations.important_declarations.iter())
+    {
+        match declaration {
+            Property::Unparsed(unparsed) => handle_tokens(&unparsed.value, &mut usages),
+            Property::Custom(custom) => {
+                handle_tokens(&custom.value, &mut usages);
+                if let CustomPropertyName::Custom(name) = &custom.name {
+                    let name = name.to_string();
+                    if !options.is_ignored(&name) {
+                        let value = declaration
+                            .value_to_css_string(printer_options())
+                            .unwrap_or_default();
+                        let mut references = vec![];
+                        handle_tokens(&custom.value, &mut references);
+                        definitions.push(CustomValue {
+                            name,
+                            value,
+                            references: references
+                                .into_iter()
+                                .map(|reference| reference.name)
+                                .filter(|name| !options.is_ignored(name))
+                                .collect(),
+                        });
+                    }
+                }
+            }
+            Property::Composes(_) => {}
+            _ => {
+                if let Ok(value) = declaration.value_to_css_string(printer_options()) {
+                    values.push((declaration.property_id().name().to_string(), value));
+                }
+            }
+        }
+    }
+
+    usages.retain(|reference| !options.is_ignored(&reference.name));
+    DeclarationBlockProperties {
+        usages,
+        definitions,
+        values,
+    }
+}
+
+// walk a token list for var() references, including ones inside functions and fallbacks
+fn handle_tokens(tokens: &TokenList, usages: &mut Vec<VarReference>) {
+    let TokenList(tokens) = tokens;
+    for token in tokens {
+        match token {
+            TokenOrValue::Var(var) => {
+                usages.push(VarReference {
+                    name: var.name.ident.to_string(),
+                    has_fallback: var.fallback.is_some(),
+                });
+                if let Some(fallback) = &var.fallback {
+                    handle_tokens(fallback, usages);
+                }
+            }
+            TokenOrValue::Env(env) => {
+                if let Some(fallback) = &env.fallback {
+                    handle_tokens(fallback, usages);
+                }
+            }
+            TokenOrValue::Function(function) => handle_tokens(&function.arguments, usages),
+            TokenOrValue::UnresolvedColor(color) => match color {
+                UnresolvedColor::RGB { alpha, .. } | UnresolvedColor::HSL { alpha, .. } => {
+                    handle_tokens(alpha, usages)
+                }
+                UnresolvedColor::LightDark { light, dark } => {
+                    handle_tokens(light, usages);
+                    handle_tokens(dark, usages);
+                }
+            },
+            _ => {}
+        }
+    }
+}
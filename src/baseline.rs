@@ -0,0 +1,196 @@
+use crate::findings::Finding;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The version of the baseline file, bumped whenever its shape changes.
+pub const BASELINE_VERSION: u32 = 1;
+
+/// Findings that are already known about, so that only new ones are reported. Findings are
+/// keyed by their rule, custom property, selector and file rather than their line, so edits
+/// elsewhere in a file don't invalidate the baseline.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    pub version: u32,
+    pub findings: Vec<Entry>,
+    /// The directory the baseline's files are relative to, which is the one it's saved in.
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+/// A known finding, along with how many times it was found. The file is relative to the
+/// baseline's directory.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    pub rule: String,
+    pub property: String,
+    pub selector: String,
+    pub file: String,
+    pub count: usize,
+}
+
+// what identifies a finding across edits: its rule, custom property, selector and file
+type Key = (String, String, String, String);
+
+// the directory a baseline at `path` keeps its files relative to
+fn root(path: &Path) -> PathBuf {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf())
+}
+
+// the same file however it was given, like v1/a.css, ./v1/a.css or an absolute path, as a path
+// relative to the baseline's directory. Files that don't exist, like <stdin>, are kept as is.
+fn normalize(file: &str, root: &Path) -> String {
+    let path = match Path::new(file).canonicalize() {
+        Ok(canonical) => match canonical.strip_prefix(root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => canonical,
+        },
+        Err(_) => PathBuf::from(file),
+    };
+    let path = path.to_string_lossy();
+    path.trim_start_matches("./").to_string()
+}
+
+impl Baseline {
+    // the key of a finding, with its file relative to the baseline's directory
+    fn key(&self, finding: &Finding) -> Key {
+        (
+            finding.rule.id.to_string(),
+            finding.property.clone(),
+            finding.selector.clone(),
+            normalize(&finding.location.path, &self.root),
+        )
+    }
+
+    /// A baseline of every one of the findings, to be saved at `path`.
+    pub fn new(findings: &[Finding], path: &Path) -> Self {
+        let mut baseline = Baseline {
+            version: BASELINE_VERSION,
+            findings: vec![],
+            root: root(path),
+        };
+        let mut counts: BTreeMap<Key, usize> = BTreeMap::new();
+        for finding in findings {
+            *counts.entry(baseline.key(finding)).or_default() += 1;
+        }
+        baseline.findings = counts
+            .into_iter()
+            .map(|((rule, property, selector, file), count)| Entry {
+                rule,
+                property,
+                selector,
+                file,
+                count,
+            })
+            .collect();
+        baseline
+    }
+
+    /// Reads a baseline written by --update-baseline.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+        let mut baseline: Baseline = serde_json::from_str(&contents)
+            .map_err(|error| format!("Failed to parse {}: {}", path.display(), error))?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(format!(
+                "{} is not a version {} baseline; update it with --update-baseline",
+                path.display(),
+                BASELINE_VERSION
+            ));
+        }
+        baseline.root = root(path);
+        Ok(baseline)
+    }
+
+    /// Writes the baseline as pretty-printed JSON, so changes to it are easy to review.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = format!("{}\n", serde_json::to_string_pretty(self).unwrap());
+        std::fs::write(path, contents)
+            .map_err(|error| format!("Failed to write {}: {}", path.display(), error))
+    }
+
+    /// The findings that aren't in the baseline, along with how many were. When something is
+    /// found more times than the baseline knows about, the extra findings are new.
+    pub fn filter(&self, findings: Vec<Finding>) -> (Vec<Finding>, usize) {
+        let mut remaining: BTreeMap<Key, usize> = self
+            .findings
+            .iter()
+            .map(|entry| {
+                let key = (
+                    entry.rule.clone(),
+                    entry.property.clone(),
+                    entry.selector.clone(),
+                    entry.file.trim_start_matches("./").to_string(),
+                );
+                (key, entry.count)
+            })
+            .collect();
+        let mut known = 0;
+        let findings = findings
+            .into_iter()
+            .filter(|finding| match remaining.get_mut(&self.key(finding)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    known += 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect();
+        (findings, known)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::findings::{Severity, UNDEFINED_CUSTOM_PROPERTY};
+    use crate::inputs::SourceLocation;
+
+    fn finding(line: u32) -> Finding {
+        Finding {
+            rule: &UNDEFINED_CUSTOM_PROPERTY,
+            severity: Severity::Warning,
+            message: "--x is used by .a but never defined".to_string(),
+            property: "--x".to_string(),
+            declaration: String::new(),
+            selector: ".a".to_string(),
+            location: SourceLocation {
+                path: "missing/a.css".to_string(),
+                line,
+                column: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn reports_findings_beyond_the_baselines_count_as_new() {
+        let baseline = Baseline::new(&[finding(1)], Path::new("baseline.json"));
+        assert_eq!(baseline.findings.len(), 1);
+        assert_eq!(baseline.findings[0].count, 1);
+
+        let (new, known) = baseline.filter(vec![finding(3), finding(7)]);
+        assert_eq!(known, 1);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].location.line, 7);
+    }
+
+    #[test]
+    fn matches_files_however_they_were_given() {
+        let baseline = Baseline::new(&[finding(1)], Path::new("baseline.json"));
+        let mut moved = finding(2);
+        moved.location.path = "./missing/a.css".to_string();
+        let (new, known) = baseline.filter(vec![moved]);
+        assert!(new.is_empty());
+        assert_eq!(known, 1);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::audit::Audit;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The custom properties used and defined by a file, or by every file in a directory.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Footprint {
+    pub path: String,
+    /// How many audited files there are, 1 for a file.
+    pub files: usize,
+    pub usages: usize,
+    pub definitions: usize,
+    /// How many times each custom property is read.
+    pub reads: BTreeMap<String, usize>,
+    /// How many times each custom property is defined.
+    pub defines: BTreeMap<String, usize>,
+    /// Whether this is the footprint of a directory rather than a file.
+    #[serde(skip)]
+    pub is_directory: bool,
+}
+
+impl Footprint {
+    /// The usage and definition counts, along with the file count for a directory.
+    pub fn summary(&self) -> String {
+        let mut counts: Vec<String> = vec![];
+        if self.is_directory {
+            counts.push(plural(self.files, "file"));
+        }
+        counts.push(plural(self.usages, "usage"));
+        counts.push(plural(self.definitions, "definition"));
+        counts.join(", ")
+    }
+
+    /// Every custom property read or defined, with how many times it's read and defined.
+    pub fn properties(&self) -> BTreeMap<&str, (usize, usize)> {
+        let mut properties: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for (property, count) in &self.reads {
+            properties.entry(property).or_default().0 += count;
+        }
+        for (property, count) in &self.defines {
+            properties.entry(property).or_default().1 += count;
+        }
+        properties
+    }
+
+    /// Each custom property read or defined, followed by how many times.
+    pub fn property_counts(&self) -> Vec<String> {
+        self.properties()
+            .into_iter()
+            .map(|(property, (reads, definitions))| {
+                let mut counts: Vec<String> = vec![];
+                if reads > 0 {
+                    counts.push(plural(reads, "read"));
+                }
+                if definitions > 0 {
+                    counts.push(plural(definitions, "definition"));
+                }
+                format!("{}  ({})", property, counts.join(", "))
+            })
+            .collect()
+    }
+
+    fn add(&mut self, other: &Footprint) {
+        self.files += other.files;
+        self.usages += other.usages;
+        self.definitions += other.definitions;
+        for (property, count) in &other.reads {
+            *self.reads.entry(property.clone()).or_default() += count;
+        }
+        for (property, count) in &other.defines {
+            *self.defines.entry(property.clone()).or_default() += count;
+        }
+    }
+}
+
+/// The footprint of each audited file and of the directories they're in.
+pub struct Breakdown {
+    pub files: Vec<Footprint>,
+    /// Every directory containing an audited file, at any depth, with the totals of the files
+    /// under it.
+    pub directories: Vec<Footprint>,
+}
+
+impl Breakdown {
+    pub fn new(audit: &Audit, inputs: &[String]) -> Self {
+        let mut files: BTreeMap<&str, Footprint> = BTreeMap::new();
+        let new_file = |path: &str| Footprint {
+            path: path.to_string(),
+            files: 1,
+            ..Footprint::default()
+        };
+        // list every input, even the ones that don't use any custom properties
+        for path in inputs {
+            files.entry(path).or_insert_with(|| new_file(path));
+        }
+        for usage in &audit.usages {
+            let file = files
+                .entry(&usage.location.path)
+                .or_insert_with(|| new_file(&usage.location.path));
+            file.usages += 1;
+            *file.reads.entry(usage.property.clone()).or_default() += 1;
+        }
+        for definition in &audit.definitions {
+            let file = files
+                .entry(&definition.location.path)
+                .or_insert_with(|| new_file(&definition.location.path));
+            file.definitions += 1;
+            *file.defines.entry(definition.property.clone()).or_default() += 1;
+        }
+
+        let mut directories: BTreeMap<String, Footprint> = BTreeMap::new();
+        for file in files.values() {
+            for directory in directories_of(&file.path) {
+                directories
+                    .entry(directory.clone())
+                    .or_insert_with(|| Footprint {
+                        path: directory,
+                        is_directory: true,
+                        ..Footprint::default()
+                    })
+                    .add(file);
+            }
+        }
+
+        Breakdown {
+            files: files.into_values().collect(),
+            directories: directories.into_values().collect(),
+        }
+    }
+}
+
+// every directory a path is in, leaving out the current and root directories
+fn directories_of(path: &str) -> Vec<String> {
+    Path::new(path)
+        .ancestors()
+        .skip(1)
+        .map(|directory| directory.to_string_lossy().to_string())
+        .filter(|directory| !matches!(directory.as_str(), "" | "." | "/"))
+        .collect()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        count => format!("{} {}s", count, noun),
+    }
+}
@@ -0,0 +1,341 @@
+use crate::findings::{self, Severity};
+use crate::inputs::ExcludePattern;
+use crate::patterns::{self, NamePattern};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+const AFTER_HELP: &str = "\
+Stylesheets may also be .vue or .svelte components, whose <style> blocks are
+audited, or JavaScript and TypeScript files, whose css`...` tagged templates are
+audited. Templates holding only declarations are audited under the selector &.
+Directories are searched recursively and glob patterns are expanded,
+skipping anything ignored by a .gitignore file. Use - to read a stylesheet from
+stdin, which is also read when no stylesheets are given and input is piped in.
+
+Without a command, the selectors using each custom property are listed, as with
+the usages command.
+
+The lint command's rules are undefined-custom-property and unused-definition
+(warnings), forbidden-prefix (an error, once --forbidden-prefix is given),
+naming-convention (a warning, once --naming-pattern is given), hardcoded-value
+(a note), and missing-fallback and hardcoded-color, which are off unless turned
+on with --rule. It exits with 1 if any error is found. Findings recorded with
+--update-baseline are left out when the same --baseline is given, keyed by rule,
+custom property, selector and file so they survive unrelated edits.
+
+Options are also read from a css-audit.toml file, or the cssaudit key of a
+package.json file, in the current directory or the closest ancestor with one:
+
+  inputs = [\"src\"]                  # audited when no stylesheets are given
+  exclude = [\"vendor\"]              # added to any --exclude
+  ignored-prefixes = [\"--__\"]       # custom properties left out of the audit
+  exclude-properties = [\"--tw-*\"]   # added to any --exclude-property
+  include-properties = [\"/^--ds-/\"] # added to any --include-property
+  formats = [{ format = \"json\", output = \"report.json\" }]
+  forbidden-prefixes = [\"--bs-\"]    # added to any --forbidden-prefix
+  rules = { missing-fallback = \"error\" }
+  naming-pattern = \"--{namespace}-{category}-{name}\"
+  naming-values = { namespace = [\"ds\"] }
+  deprecated-properties = [\"--legacy-*\"] # added to any diff --deprecated
+  baseline = \"css-audit-baseline.json\"   # used when no --baseline is given
+  targets = { safari = \"15.4\" }     # browsers that values are printed for
+
+A declaration that follows a /* css-audit-ignore */ comment is left out of the
+audit.
+
+Files that can't be read or parsed are reported on stderr and left out of the
+audit. The rest are still audited, but the exit code is 3. When the command
+can't run at all, such as for an invalid option or config file, inputs that
+match no files or a report that can't be written, the exit code is 2.
+
+Examples:
+  css-audit styles.css
+  css-audit --format=html styles.css
+  css-audit --format=json styles.css
+  css-audit --markup=index.html styles.css
+  css-audit --exclude=vendor 'src/**/*.css'
+  cat styles.css | css-audit --stdin-filename=styles.css
+  css-audit definitions src
+  css-audit lint --format=sarif src
+  css-audit lint --baseline=css-audit-baseline.json --update-baseline src
+  css-audit diff old.json new.json
+  css-audit diff --deprecated='--legacy-*' v1/src v2/src
+  css-audit graph src | dot -Tsvg > graph.svg
+  css-audit --format=json --output=report.json --format=html --output=report.html src";
+
+/// Parses one or more CSS stylesheets and outputs a list of custom properties and the
+/// selectors that use them.
+#[derive(Parser)]
+#[command(
+    name = "css-audit",
+    bin_name = "css-audit",
+    version,
+    after_help = AFTER_HELP,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Read the configuration from FILE, a css-audit.toml file or a package.json file with a
+    /// cssaudit key, instead of looking for one
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub usages: UsagesArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the selectors using each custom property (the default)
+    Usages(UsagesArgs),
+    /// List the selectors defining each custom property, with their values
+    Definitions(DefinitionsArgs),
+    /// Report problems such as undefined, unused or hardcoded custom properties
+    Lint(LintArgs),
+    /// Compare two JSON reports produced by --format=json, or two sets of stylesheets
+    Diff(DiffArgs),
+    /// Output which custom properties are defined in terms of others
+    Graph(GraphArgs),
+}
+
+/// The files to audit.
+#[derive(Args)]
+pub struct InputArgs {
+    /// Stylesheets, components, directories or glob patterns to audit; - reads from stdin
+    #[arg(value_name = "STYLESHEET|DIRECTORY|GLOB")]
+    pub inputs: Vec<String>,
+
+    /// Skip files matching the .gitignore-style pattern; may be given more than once
+    #[arg(long, value_name = "GLOB", value_parser = ExcludePattern::parse)]
+    pub exclude: Vec<ExcludePattern>,
+
+    /// Only audit custom properties whose names match the glob, like --color-*, or the
+    /// regular expression between slashes, like /^--(color|space)-/; may be given more than once
+    #[arg(long, value_name = "PATTERN", value_parser = NamePattern::parse)]
+    pub include_property: Vec<NamePattern>,
+
+    /// Leave out custom properties whose names match the glob, like --tw-*, or the regular
+    /// expression between slashes; may be given more than once
+    #[arg(long, value_name = "PATTERN", value_parser = NamePattern::parse)]
+    pub exclude_property: Vec<NamePattern>,
+
+    /// The file name to report for a stylesheet read from stdin
+    #[arg(long, value_name = "NAME", default_value = "<stdin>")]
+    pub stdin_filename: String,
+
+    /// Skip invalid rules and declarations, such as vendor hacks, and audit the rest of the
+    /// file instead of leaving it out; each one skipped is reported as a warning
+    #[arg(long)]
+    pub error_recovery: bool,
+}
+
+#[derive(Args)]
+pub struct UsagesArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// How to output the audit, terminal unless it's configured; may be given more than once,
+    /// along with an --output for each
+    #[arg(long, value_enum)]
+    pub format: Vec<OutputFormats>,
+
+    /// Write the output of the --format given before it to FILE instead of stdout; may be
+    /// given more than once
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+
+    /// Report style rules that match no element in the HTML file instead of the custom
+    /// properties, so it can't be combined with the options shaping that report; may be given
+    /// more than once
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["by_selector", "by_file", "summary", "legacy_json"]
+    )]
+    pub markup: Vec<String>,
+
+    /// List the custom properties each selector reads and defines instead of the selectors
+    /// using each custom property
+    #[arg(long, conflicts_with = "by_file")]
+    pub by_selector: bool,
+
+    /// List how many custom properties each directory and file reads and defines instead
+    #[arg(long)]
+    pub by_file: bool,
+
+    /// Only output statistics about the custom properties, which are otherwise included with
+    /// everything else; has no effect on HTML and SARIF output
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Output JSON as the unversioned list of {selector, rules} objects used before
+    /// schema_version 1
+    #[arg(long)]
+    pub legacy_json: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormats {
+    /// Output to the terminal
+    Terminal,
+    /// Output a JSON document
+    Json,
+    /// Output an HTML document
+    Html,
+    /// Output findings such as undefined or unused custom properties as a SARIF 2.1.0 log
+    Sarif,
+    /// Output one comma-separated row per custom property usage
+    Csv,
+    /// Output one tab-separated row per custom property usage
+    Tsv,
+    /// Output Markdown headings and tables with usage counts
+    Markdown,
+    /// Do not output anything (useful for testing)
+    None,
+}
+
+#[derive(Args)]
+pub struct DefinitionsArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// How to output the definitions; may be given more than once, along with an --output for each
+    #[arg(long, value_enum, default_values_t = [DefinitionFormats::Terminal])]
+    pub format: Vec<DefinitionFormats>,
+
+    /// Write the output of the --format given before it to FILE instead of stdout; may be
+    /// given more than once
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DefinitionFormats {
+    /// Output to the terminal
+    Terminal,
+    /// Output a JSON document
+    Json,
+    /// Output one comma-separated row per definition
+    Csv,
+    /// Output one tab-separated row per definition
+    Tsv,
+    /// Do not output anything (useful for testing)
+    None,
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Set the severity of a rule's findings to off, note, warning or error; may be given more
+    /// than once. Findings at error severity make the exit code 1
+    #[arg(long, value_name = "RULE=SEVERITY", value_parser = findings::parse_rule_setting)]
+    pub rule: Vec<(String, Option<Severity>)>,
+
+    /// Report custom properties whose names start with PREFIX, like --bs-; may be given more
+    /// than once
+    #[arg(long, value_name = "PREFIX")]
+    pub forbidden_prefix: Vec<String>,
+
+    /// Report custom properties whose names don't follow PATTERN, where each {placeholder}
+    /// stands for a kebab-case word, like --{namespace}-{category}-{name}
+    #[arg(long, value_name = "PATTERN", value_parser = patterns::parse_naming_pattern)]
+    pub naming_pattern: Option<String>,
+
+    /// Only allow the comma-separated VALUES for a placeholder of the naming pattern, like
+    /// namespace=ds,app; may be given more than once
+    #[arg(long, value_name = "PLACEHOLDER=VALUES", value_parser = patterns::parse_naming_values)]
+    pub naming_values: Vec<(String, Vec<String>)>,
+
+    /// Leave out the findings recorded in FILE, so only new ones are reported and decide the
+    /// exit code
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+
+    /// Record the current findings in the --baseline file instead, replacing what's there
+    #[arg(long)]
+    pub update_baseline: bool,
+
+    /// How to output the findings; may be given more than once, along with an --output for each
+    #[arg(long, value_enum, default_values_t = [LintFormats::Terminal])]
+    pub format: Vec<LintFormats>,
+
+    /// Write the output of the --format given before it to FILE instead of stdout; may be
+    /// given more than once
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LintFormats {
+    /// Output to the terminal
+    Terminal,
+    /// Output a JSON document
+    Json,
+    /// Output a SARIF 2.1.0 log
+    Sarif,
+    /// Do not output anything (useful for testing)
+    None,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The JSON report to compare against, or the stylesheets, directory or glob pattern to
+    /// audit for it
+    #[arg(value_name = "OLD")]
+    pub old: String,
+
+    /// The JSON report to compare, or the stylesheets, directory or glob pattern to audit for it
+    #[arg(value_name = "NEW")]
+    pub new: String,
+
+    /// Report selectors that start reading custom properties whose names match the glob, like
+    /// --legacy-*, or the regular expression between slashes; may be given more than once
+    #[arg(long, value_name = "PATTERN", value_parser = NamePattern::parse)]
+    pub deprecated: Vec<NamePattern>,
+
+    /// How to output the differences; may be given more than once, along with an --output for each
+    #[arg(long, value_enum, default_values_t = [DiffFormats::Terminal])]
+    pub format: Vec<DiffFormats>,
+
+    /// Write the output of the --format given before it to FILE instead of stdout; may be
+    /// given more than once
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DiffFormats {
+    /// Output to the terminal
+    Terminal,
+    /// Output a JSON document
+    Json,
+}
+
+#[derive(Args)]
+pub struct GraphArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// How to output the graph; may be given more than once, along with an --output for each
+    #[arg(long, value_enum, default_values_t = [GraphFormats::Dot])]
+    pub format: Vec<GraphFormats>,
+
+    /// Write the output of the --format given before it to FILE instead of stdout; may be
+    /// given more than once
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GraphFormats {
+    /// Output a Graphviz DOT graph
+    Dot,
+    /// Output a Mermaid flowchart
+    Mermaid,
+    /// Output a JSON document of nodes and edges
+    Json,
+}
@@ -0,0 +1,294 @@
+use crate::cli::OutputFormats;
+use crate::findings::{self, Severity};
+use crate::inputs::ExcludePattern;
+use crate::patterns::NamePattern;
+use clap::ValueEnum;
+use lightningcss::targets::{Browsers, Targets};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The name of the config file looked for in the current directory and its ancestors.
+pub const CONFIG_FILE: &str = "css-audit.toml";
+
+/// The key of the config in a package.json file, used when there's no css-audit.toml.
+pub const PACKAGE_JSON_KEY: &str = "cssaudit";
+
+/// The custom property name prefixes left out of the audit unless they're configured, for
+/// properties that are private to a component.
+pub const DEFAULT_IGNORED_PREFIXES: &[&str] = &["--__"];
+
+/// A project's configuration, read from css-audit.toml or the `cssaudit` key of package.json.
+/// Options given on the command line take precedence.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Stylesheets, directories or glob patterns to audit when none are given, relative to the
+    /// config file.
+    pub inputs: Vec<String>,
+    /// Patterns of files to skip, added to any given with --exclude. Patterns containing a slash
+    /// are relative to the config file.
+    pub exclude: Vec<String>,
+    /// Custom property name prefixes to leave out of the audit, replacing the default `--__`.
+    pub ignored_prefixes: Option<Vec<String>>,
+    /// Globs or slash-delimited regular expressions of the custom property names to audit,
+    /// added to any given with --include-property.
+    pub include_properties: Vec<String>,
+    /// Globs or slash-delimited regular expressions of custom property names to leave out of
+    /// the audit, added to any given with --exclude-property.
+    pub exclude_properties: Vec<String>,
+    /// The formats to output when none are given with --format, each with an optional file.
+    pub formats: Vec<FormatConfig>,
+    /// Lint rule severities keyed by rule id, overridden by any given with --rule.
+    pub rules: BTreeMap<String, String>,
+    /// Custom property name prefixes reported by the forbidden-prefix rule.
+    pub forbidden_prefixes: Vec<String>,
+    /// The naming convention checked by the naming-convention rule, like
+    /// `--{namespace}-{category}-{name}`, replaced by any given with --naming-pattern.
+    pub naming_pattern: Option<String>,
+    /// The values allowed for each of the naming pattern's placeholders, like
+    /// `namespace = ["ds"]`, replaced by any given with --naming-values.
+    pub naming_values: BTreeMap<String, Vec<String>>,
+    /// Globs or slash-delimited regular expressions of deprecated custom property names,
+    /// added to any given with diff --deprecated.
+    pub deprecated_properties: Vec<String>,
+    /// The lint baseline used when none is given with --baseline, relative to the config file.
+    pub baseline: Option<PathBuf>,
+    /// The oldest version of each browser to support, like `safari = "15.4"`, which decides how
+    /// values like colors are printed.
+    pub targets: BTreeMap<String, String>,
+    /// Where the config was read from, if anywhere. Relative paths are resolved against its
+    /// directory.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatConfig {
+    pub format: String,
+    pub output: Option<PathBuf>,
+}
+
+impl Config {
+    // the directory relative paths in the config are resolved against
+    fn directory(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    // add the config file to an error about one of its values
+    fn invalid(&self, error: String) -> String {
+        format!("Invalid {}: {}", self.path.display(), error)
+    }
+
+    /// The inputs, resolved against the directory the config was found in.
+    pub fn inputs(&self) -> Vec<String> {
+        self.inputs
+            .iter()
+            .map(|input| self.directory().join(input).to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// The lint baseline, resolved against the directory the config was found in.
+    pub fn baseline(&self) -> Option<PathBuf> {
+        self.baseline
+            .as_ref()
+            .map(|path| self.directory().join(path))
+    }
+
+    /// The patterns of files to skip, with any containing a slash anchored to the directory the
+    /// config was found in.
+    pub fn excludes(&self) -> Result<Vec<ExcludePattern>, String> {
+        self.exclude
+            .iter()
+            .map(|pattern| {
+                ExcludePattern::parse(pattern)
+                    .map(|pattern| pattern.relative_to(self.directory()))
+                    .map_err(|error| self.invalid(format!("exclude: {}", error)))
+            })
+            .collect()
+    }
+
+    /// The custom property name prefixes to leave out of the audit.
+    pub fn ignored_prefixes(&self) -> Vec<String> {
+        match &self.ignored_prefixes {
+            Some(prefixes) => prefixes.clone(),
+            None => DEFAULT_IGNORED_PREFIXES
+                .iter()
+                .map(|prefix| prefix.to_string())
+                .collect(),
+        }
+    }
+
+    /// The patterns of the custom property names to audit.
+    pub fn include_properties(&self) -> Result<Vec<NamePattern>, String> {
+        self.name_patterns(&self.include_properties, "include-properties")
+    }
+
+    /// The patterns of custom property names to leave out of the audit.
+    pub fn exclude_properties(&self) -> Result<Vec<NamePattern>, String> {
+        self.name_patterns(&self.exclude_properties, "exclude-properties")
+    }
+
+    /// The patterns of deprecated custom property names.
+    pub fn deprecated_properties(&self) -> Result<Vec<NamePattern>, String> {
+        self.name_patterns(&self.deprecated_properties, "deprecated-properties")
+    }
+
+    // parse each pattern, naming the key it's from in any error
+    fn name_patterns(&self, patterns: &[String], key: &str) -> Result<Vec<NamePattern>, String> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                NamePattern::parse(pattern)
+                    .map_err(|error| self.invalid(format!("{}: {}", key, error)))
+            })
+            .collect()
+    }
+
+    /// Each configured format along with the file to write it to, if any.
+    pub fn formats(&self) -> Result<Vec<(OutputFormats, Option<PathBuf>)>, String> {
+        self.formats
+            .iter()
+            .map(|format| {
+                let output = format
+                    .output
+                    .as_ref()
+                    .map(|path| self.directory().join(path));
+                let format = OutputFormats::from_str(&format.format, false).map_err(|_| {
+                    let formats: Vec<String> = OutputFormats::value_variants()
+                        .iter()
+                        .filter_map(|format| format.to_possible_value())
+                        .map(|format| format.get_name().to_string())
+                        .collect();
+                    self.invalid(format!(
+                        "unknown format '{}' in formats [possible formats: {}]",
+                        format.format,
+                        formats.join(", ")
+                    ))
+                })?;
+                Ok((format, output))
+            })
+            .collect()
+    }
+
+    /// The configured rule severities, checking each rule id and severity.
+    pub fn rules(&self) -> Result<BTreeMap<String, Option<Severity>>, String> {
+        self.rules
+            .iter()
+            .map(|(id, severity)| {
+                findings::parse_rule_setting(&format!("{}={}", id, severity))
+                    .map_err(|error| self.invalid(error))
+            })
+            .collect()
+    }
+
+    /// The browser targets, parsing each version as `major`, `major.minor` or
+    /// `major.minor.patch`.
+    pub fn targets(&self) -> Result<Targets, String> {
+        if self.targets.is_empty() {
+            return Ok(Targets::default());
+        }
+        let mut browsers = Browsers::default();
+        for (browser, version) in &self.targets {
+            let target = match browser.as_str() {
+                "android" => &mut browsers.android,
+                "chrome" => &mut browsers.chrome,
+                "edge" => &mut browsers.edge,
+                "firefox" => &mut browsers.firefox,
+                "ie" => &mut browsers.ie,
+                "ios_saf" => &mut browsers.ios_saf,
+                "opera" => &mut browsers.opera,
+                "safari" => &mut browsers.safari,
+                "samsung" => &mut browsers.samsung,
+                _ => {
+                    return Err(self.invalid(format!(
+                        "unknown browser '{}' in targets [possible browsers: android, chrome, edge, firefox, ie, ios_saf, opera, safari, samsung]",
+                        browser
+                    )))
+                }
+            };
+            *target = Some(parse_version(version).ok_or_else(|| {
+                self.invalid(format!(
+                    "invalid version '{}' for {} in targets",
+                    version, browser
+                ))
+            })?);
+        }
+        Ok(Targets::from(browsers))
+    }
+}
+
+/// Loads the config file at `path`, or finds css-audit.toml or a package.json with a `cssaudit`
+/// key in the current directory or the closest ancestor with either. Without one, the default
+/// config is used.
+pub fn load(path: Option<&Path>) -> Result<Config, String> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => match find() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        },
+    };
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+    let mut config: Config = if path.file_name().is_some_and(|name| name == "package.json") {
+        let mut package: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|error| format!("Failed to parse {}: {}", path.display(), error))?;
+        serde_json::from_value(package[PACKAGE_JSON_KEY].take()).map_err(|error| {
+            format!(
+                "Failed to parse the {} key of {}: {}",
+                PACKAGE_JSON_KEY,
+                path.display(),
+                error
+            )
+        })?
+    } else {
+        toml::from_str(&contents)
+            .map_err(|error| format!("Failed to parse {}: {}", path.display(), error))?
+    };
+    config.path = path;
+    Ok(config)
+}
+
+// the closest css-audit.toml, or package.json with a cssaudit key, in the current directory
+// or one of its ancestors
+fn find() -> Option<PathBuf> {
+    let current = std::env::current_dir().ok()?;
+    for directory in current.ancestors() {
+        let config = directory.join(CONFIG_FILE);
+        if config.is_file() {
+            return Some(relative(&current, config));
+        }
+        let package = directory.join("package.json");
+        let has_key = std::fs::read_to_string(&package)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+            .is_some_and(|package| package.get(PACKAGE_JSON_KEY).is_some());
+        if has_key {
+            return Some(relative(&current, package));
+        }
+    }
+    None
+}
+
+// keep paths found in the current directory relative, so the inputs resolved against it are too
+fn relative(current: &Path, path: PathBuf) -> PathBuf {
+    match path.strip_prefix(current) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path,
+    }
+}
+
+// lightningcss stores versions as major << 16 | minor << 8 | patch
+fn parse_version(version: &str) -> Option<u32> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() || minor > 255 || patch > 255 {
+        return None;
+    }
+    Some(major << 16 | minor << 8 | patch)
+}
@@ -0,0 +1,188 @@
+use lightningcss::properties::css_modules::Specifier;
+use lightningcss::properties::Property;
+use lightningcss::rules::style::StyleRule;
+use lightningcss::selector::{Component, PseudoClass, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// A class exported by a CSS module.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ModuleClass {
+    /// The `:global(...)` selectors the class is used alongside.
+    pub global_contexts: BTreeSet<String>,
+    /// The classes pulled in by `composes` declarations, as written.
+    pub composes: BTreeSet<String>,
+    /// Classes in the same module that are composed, used to inherit their custom properties.
+    #[serde(skip)]
+    local_composes: BTreeSet<String>,
+    /// Custom properties used by rules that select the class directly, like `.title` in
+    /// `.card .title` rather than `.card`.
+    pub custom_properties: BTreeSet<String>,
+}
+
+/// The exported classes of every CSS module that was audited, keyed by path and class name.
+#[derive(Default)]
+pub struct CssModules {
+    pub modules: BTreeMap<String, BTreeMap<String, ModuleClass>>,
+}
+
+impl CssModules {
+    /// Attributes any `composes` declarations in a style rule to the local classes in its
+    /// selectors, and the custom properties it uses to the local classes it styles, like `.title`
+    /// but not `.card` in `.card .title`.
+    pub fn record_rule<'a>(
+        &mut self,
+        path: &str,
+        rule: &StyleRule,
+        custom_properties: impl Iterator<Item = &'a String>,
+    ) {
+        let mut locals: Vec<String> = vec![];
+        let mut globals: Vec<String> = vec![];
+        let mut subjects: Vec<String> = vec![];
+        for selector in rule.selectors.0.iter() {
+            collect_classes(selector, &mut locals, &mut globals);
+            collect_subject_classes(selector, &mut subjects);
+        }
+        if locals.is_empty() {
+            return;
+        }
+
+        let mut composes: Vec<(String, bool)> = vec![];
+        for declaration in &rule.declarations.declarations {
+            if let Property::Composes(declaration) = declaration {
+                for name in declaration.names.iter() {
+                    composes.push(match &declaration.from {
+                        None => (name.0.to_string(), true),
+                        Some(Specifier::Global) => (format!("{} from global", name.0), false),
+                        Some(Specifier::File(file)) => {
+                            (format!("{} from \"{}\"", name.0, file), false)
+                        }
+                        Some(Specifier::SourceIndex(index)) => {
+                            (format!("{} from source {}", name.0, index), false)
+                        }
+                    });
+                }
+            }
+        }
+
+        let custom_properties: Vec<&String> = custom_properties.collect();
+        let classes = self.modules.entry(path.to_string()).or_default();
+        for local in locals {
+            let is_subject = subjects.contains(&local);
+            let class = classes.entry(local).or_default();
+            class.global_contexts.extend(globals.iter().cloned());
+            for (name, is_local) in &composes {
+                class.composes.insert(name.clone());
+                if *is_local {
+                    class.local_composes.insert(name.clone());
+                }
+            }
+            if is_subject {
+                class.custom_properties.extend(
+                    custom_properties
+                        .iter()
+                        .map(|property| property.to_string()),
+                );
+            }
+        }
+    }
+
+    /// Lists each exported class with its `:global` context, what it composes, and the custom
+    /// properties it uses, including those inherited from classes it composes.
+    pub fn groups(&self) -> Vec<(String, Vec<String>)> {
+        let mut groups: Vec<(String, Vec<String>)> = vec![];
+        for (path, classes) in &self.modules {
+            for (name, class) in classes {
+                let mut entries: Vec<String> = vec![];
+                for context in &class.global_contexts {
+                    entries.push(format!(":global({})", context));
+                }
+                for composed in &class.composes {
+                    entries.push(format!("composes {}", composed));
+                }
+                entries.extend(class.custom_properties.iter().cloned());
+                for (property, via) in inherited_custom_properties(classes, class) {
+                    entries.push(format!("{} (via {})", property, via));
+                }
+                groups.push((format!("{} .{}", path, name), entries));
+            }
+        }
+        groups
+    }
+}
+
+// walk the classes composed from the same module, collecting the custom properties that the
+// class doesn't already use directly along with the class they came from
+fn inherited_custom_properties(
+    classes: &BTreeMap<String, ModuleClass>,
+    class: &ModuleClass,
+) -> BTreeMap<String, String> {
+    let mut inherited: BTreeMap<String, String> = BTreeMap::new();
+    let mut visited: HashSet<&String> = HashSet::new();
+    let mut queue: Vec<&String> = class.local_composes.iter().collect();
+    while let Some(name) = queue.pop() {
+        if !visited.insert(name) {
+            continue;
+        }
+        if let Some(composed) = classes.get(name) {
+            for property in &composed.custom_properties {
+                if !class.custom_properties.contains(property) {
+                    inherited
+                        .entry(property.clone())
+                        .or_insert_with(|| name.clone());
+                }
+            }
+            queue.extend(composed.local_composes.iter());
+        }
+    }
+    inherited
+}
+
+// collect the local class names in a selector, and the `:global(...)` selectors alongside them
+fn collect_classes(selector: &Selector, locals: &mut Vec<String>, globals: &mut Vec<String>) {
+    for component in selector.iter_raw_match_order() {
+        match component {
+            Component::Class(name) => locals.push(name.0.to_string()),
+            Component::NonTSPseudoClass(PseudoClass::Global { selector }) => {
+                globals.push(crate::to_css(selector.as_ref()));
+            }
+            Component::NonTSPseudoClass(PseudoClass::Local { selector }) => {
+                collect_classes(selector, locals, globals);
+            }
+            Component::Negation(selectors)
+            | Component::Is(selectors)
+            | Component::Where(selectors)
+            | Component::Has(selectors)
+            | Component::Any(_, selectors) => {
+                for selector in selectors.iter() {
+                    collect_classes(selector, locals, globals);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// collect the local class names in the compound selector that the rule styles, which is the last
+// one, like .title in .card .title. Classes the element is only matched against, like those in
+// :not() or :has(), are left out.
+fn collect_subject_classes(selector: &Selector, subjects: &mut Vec<String>) {
+    for component in selector.iter_raw_match_order() {
+        match component {
+            // a pseudo-element is styled on behalf of the element before it
+            Component::Combinator(combinator) if !combinator.is_pseudo_element() => break,
+            Component::Class(name) => subjects.push(name.0.to_string()),
+            Component::NonTSPseudoClass(PseudoClass::Local { selector }) => {
+                collect_subject_classes(selector, subjects);
+            }
+            Component::Is(selectors)
+            | Component::Where(selectors)
+            | Component::Any(_, selectors) => {
+                for selector in selectors.iter() {
+                    collect_subject_classes(selector, subjects);
+                }
+            }
+            _ => {}
+        }
+    }
+}
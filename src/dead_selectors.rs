@@ -0,0 +1,369 @@
+use crate::findings::{Config, Finding, DEAD_RULE};
+use crate::inputs::SourceLocation;
+use lightningcss::selector::{PseudoClass, Selector};
+use lightningcss::values::ident::Ident;
+use lightningcss::values::string::CowArcStr;
+use parcel_selectors::attr::{AttrSelectorOperation, CaseSensitivity, NamespaceConstraint};
+use parcel_selectors::context::{MatchingContext, MatchingMode, QuirksMode};
+use parcel_selectors::matching::{matches_selector, ElementSelectorFlags};
+use parcel_selectors::parser::Component;
+use parcel_selectors::{Element, OpaqueElement, SelectorImpl};
+use scraper::{ElementRef, Html};
+use serde::Serialize;
+use std::fmt;
+
+/// lightningcss keeps its `SelectorImpl` private, so name it through the selector type instead.
+trait HasSelectorImpl {
+    type Impl;
+}
+
+impl<'i, Impl: SelectorImpl<'i>> HasSelectorImpl for parcel_selectors::parser::Selector<'i, Impl> {
+    type Impl = Impl;
+}
+
+type Selectors = <Selector<'static> as HasSelectorImpl>::Impl;
+
+/// A style rule whose selectors don't match any element in the markup fixtures.
+#[derive(Serialize)]
+pub struct DeadRule {
+    pub selector: String,
+    pub location: SourceLocation,
+    pub custom_properties: Vec<String>,
+}
+
+/// The result of checking every style rule against the markup fixtures.
+#[derive(Serialize)]
+pub struct DeadSelectorReport {
+    pub rules: Vec<DeadRule>,
+    /// Custom properties that are only used by dead rules, and so would become
+    /// unused if those rules were deleted.
+    pub orphaned_custom_properties: Vec<String>,
+}
+
+impl DeadSelectorReport {
+    /// Lists each dead rule as a finding, for output formats that only report findings.
+    pub fn findings(&self, config: &Config) -> Vec<Finding> {
+        let Some(severity) = config.severity(&DEAD_RULE) else {
+            return vec![];
+        };
+        self.rules
+            .iter()
+            .map(|rule| Finding {
+                rule: &DEAD_RULE,
+                severity,
+                // messages are a single line, so put the at-rules on the selector's line
+                message: format!(
+                    "{} doesn't match any element in the markup",
+                    rule.selector
+                        .lines()
+                        .map(str::trim)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+                property: rule.custom_properties.first().cloned().unwrap_or_default(),
+                declaration: String::new(),
+                selector: rule.selector.clone(),
+                location: rule.location.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Parses every markup fixture into a document that selectors can be matched against, failing
+/// on the first one that can't be read.
+pub fn load_documents(paths: &[String]) -> Result<Vec<Html>, String> {
+    paths
+        .iter()
+        .map(|path| {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|error| format!("Failed to read markup {}: {}", path, error))?;
+            Ok(Html::parse_document(&contents))
+        })
+        .collect()
+}
+
+/// Selectors with more distinct state pseudo-classes than this are assumed to match, rather
+/// than trying every combination of them.
+const MAX_PSEUDO_CLASSES: usize = 8;
+
+/// Returns true if `selector` matches at least one element in any of `documents`.
+///
+/// Static markup can't tell us whether an element is hovered, focused, checked and so on, so
+/// the selector matches if it would with any combination of its non tree-structural
+/// pseudo-classes matching or not. That way both `.btn:hover` and `.btn:not(:hover)` match a
+/// `.btn`.
+pub fn matches_any(selector: &Selector, documents: &[Html]) -> bool {
+    let mut pseudo_classes: Vec<&PseudoClass> = vec![];
+    collect_pseudo_classes(selector, &mut pseudo_classes);
+    if pseudo_classes.len() > MAX_PSEUDO_CLASSES {
+        return true;
+    }
+    // each bit of `matching` is whether the pseudo-class at that index matches
+    (0..1u32 << pseudo_classes.len()).any(|matching| {
+        documents.iter().any(|document| {
+            document
+                .tree
+                .nodes()
+                .filter_map(ElementRef::wrap)
+                .any(|element| {
+                    let mut context = MatchingContext::new(
+                        MatchingMode::Normal,
+                        None,
+                        None,
+                        QuirksMode::NoQuirks,
+                    );
+                    let element = MarkupElement {
+                        element,
+                        pseudo_classes: &pseudo_classes,
+                        matching,
+                    };
+                    matches_selector(selector, 0, None, &element, &mut context, &mut |_, _| {})
+                })
+        })
+    })
+}
+
+// gather the distinct non tree-structural pseudo-classes in the selector, including those in
+// selectors nested in it, like :not(:hover)
+fn collect_pseudo_classes<'a, 'i>(
+    selector: &'a Selector<'i>,
+    pseudo_classes: &mut Vec<&'a PseudoClass<'i>>,
+) {
+    for component in selector.iter_raw_match_order() {
+        let nested: &[Selector] = match component {
+            Component::NonTSPseudoClass(pseudo_class) => {
+                if !pseudo_classes.contains(&pseudo_class) {
+                    pseudo_classes.push(pseudo_class);
+                }
+                &[]
+            }
+            Component::Negation(selectors)
+            | Component::Is(selectors)
+            | Component::Where(selectors)
+            | Component::Has(selectors)
+            | Component::Any(_, selectors) => selectors,
+            Component::NthOf(nth_of) => nth_of.selectors(),
+            Component::Slotted(selector) | Component::Host(Some(selector)) => {
+                std::slice::from_ref(selector)
+            }
+            _ => &[],
+        };
+        for selector in nested {
+            collect_pseudo_classes(selector, pseudo_classes);
+        }
+    }
+}
+
+/// An element from a markup fixture, wrapped so `parcel_selectors` can match against it.
+///
+/// Each of the selector's pseudo-classes matches or not as `matching` says, see
+/// [`matches_any`]. Pseudo-elements are treated as the element that generates them, which
+/// keeps the report from flagging rules that are actually in use.
+#[derive(Clone)]
+struct MarkupElement<'a, 'i> {
+    element: ElementRef<'a>,
+    pseudo_classes: &'a [&'a PseudoClass<'i>],
+    matching: u32,
+}
+
+// lightningcss's pseudo-classes aren't Debug, so leave them out
+impl fmt::Debug for MarkupElement<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MarkupElement")
+            .field("element", &self.element)
+            .field("matching", &self.matching)
+            .finish()
+    }
+}
+
+impl<'a, 'i> MarkupElement<'a, 'i> {
+    // another element of the same document, with the same pseudo-classes matching
+    fn with(&self, element: Option<ElementRef<'a>>) -> Option<Self> {
+        element.map(|element| MarkupElement {
+            element,
+            pseudo_classes: self.pseudo_classes,
+            matching: self.matching,
+        })
+    }
+}
+
+impl<'a, 'i> Element<'i> for MarkupElement<'a, 'i> {
+    type Impl = Selectors;
+
+    fn opaque(&self) -> OpaqueElement {
+        OpaqueElement::new(self.element.value())
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        self.with(self.element.parent().and_then(ElementRef::wrap))
+    }
+
+    fn parent_node_is_shadow_root(&self) -> bool {
+        false
+    }
+
+    fn containing_shadow_host(&self) -> Option<Self> {
+        None
+    }
+
+    fn pseudo_element_originating_element(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+
+    fn is_pseudo_element(&self) -> bool {
+        false
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.with(self.element.prev_siblings().find_map(ElementRef::wrap))
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.with(self.element.next_siblings().find_map(ElementRef::wrap))
+    }
+
+    fn is_html_element_in_html_document(&self) -> bool {
+        true
+    }
+
+    fn has_local_name(&self, local_name: &Ident<'i>) -> bool {
+        self.element
+            .value()
+            .name()
+            .eq_ignore_ascii_case(&local_name.0)
+    }
+
+    fn has_namespace(&self, ns: &CowArcStr<'i>) -> bool {
+        *self.element.value().name.ns == **ns
+    }
+
+    fn is_same_type(&self, other: &Self) -> bool {
+        self.element.value().name == other.element.value().name
+    }
+
+    fn attr_matches(
+        &self,
+        _ns: &NamespaceConstraint<&<Self::Impl as SelectorImpl<'i>>::NamespaceUrl>,
+        local_name: &<Self::Impl as SelectorImpl<'i>>::LocalName,
+        operation: &AttrSelectorOperation<&<Self::Impl as SelectorImpl<'i>>::AttrValue>,
+    ) -> bool {
+        match self.element.value().attr(&local_name.0) {
+            Some(value) => match operation {
+                AttrSelectorOperation::Exists => true,
+                AttrSelectorOperation::WithValue {
+                    operator,
+                    case_sensitivity,
+                    expected_value,
+                } => operator.eval_str(value, &expected_value.0, *case_sensitivity),
+            },
+            None => false,
+        }
+    }
+
+    fn match_non_ts_pseudo_class<F>(
+        &self,
+        pc: &<Self::Impl as SelectorImpl<'i>>::NonTSPseudoClass,
+        _context: &mut MatchingContext<'_, 'i, Self::Impl>,
+        _flags_setter: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, ElementSelectorFlags),
+    {
+        self.pseudo_classes
+            .iter()
+            .position(|pseudo_class| *pseudo_class == pc)
+            .is_none_or(|index| self.matching & (1 << index) != 0)
+    }
+
+    fn match_pseudo_element(
+        &self,
+        _pe: &<Self::Impl as SelectorImpl<'i>>::PseudoElement,
+        _context: &mut MatchingContext<'_, 'i, Self::Impl>,
+    ) -> bool {
+        true
+    }
+
+    fn is_link(&self) -> bool {
+        matches!(self.element.value().name(), "a" | "area" | "link")
+            && self.element.value().attr("href").is_some()
+    }
+
+    fn is_html_slot_element(&self) -> bool {
+        self.element.value().name() == "slot"
+    }
+
+    fn has_id(&self, id: &Ident<'i>, case_sensitivity: CaseSensitivity) -> bool {
+        self.element
+            .value()
+            .id()
+            .is_some_and(|value| case_sensitivity.eq(value.as_bytes(), id.0.as_bytes()))
+    }
+
+    fn has_class(&self, name: &Ident<'i>, case_sensitivity: CaseSensitivity) -> bool {
+        self.element
+            .value()
+            .classes()
+            .any(|class| case_sensitivity.eq(class.as_bytes(), name.0.as_bytes()))
+    }
+
+    fn imported_part(&self, _name: &Ident<'i>) -> Option<Ident<'i>> {
+        None
+    }
+
+    fn is_part(&self, _name: &Ident<'i>) -> bool {
+        false
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.element.children().any(|child| match child.value() {
+            scraper::Node::Element(_) => true,
+            scraper::Node::Text(text) => !text.is_empty(),
+            _ => false,
+        })
+    }
+
+    fn is_root(&self) -> bool {
+        self.element
+            .parent()
+            .is_some_and(|parent| parent.value().is_document())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightningcss::rules::CssRule;
+    use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+
+    // whether the rule's selector matches any element in the markup
+    fn matches(selector: &str, markup: &str) -> bool {
+        let css = format!("{} {{}}", selector);
+        let stylesheet = StyleSheet::parse(&css, ParserOptions::default()).unwrap();
+        let CssRule::Style(rule) = &stylesheet.rules.0[0] else {
+            panic!("{} isn't a style rule", selector);
+        };
+        matches_any(&rule.selectors.0[0], &[Html::parse_document(markup)])
+    }
+
+    #[test]
+    fn matches_classes_and_structure() {
+        let markup = "<main><ul class=\"list\"><li>a</li><li class=\"b\">b</li></ul></main>";
+        assert!(matches(".list > li:first-child", markup));
+        assert!(matches("main .b:last-child", markup));
+        assert!(matches("li:not(.b)", markup));
+        assert!(!matches(".list > .b:first-child", markup));
+        assert!(!matches(".missing", markup));
+    }
+
+    #[test]
+    fn matches_state_pseudo_classes_either_way() {
+        let markup = "<button class=\"btn\">Save</button>";
+        assert!(matches(".btn:hover", markup));
+        assert!(matches(".btn:not(:hover)", markup));
+        assert!(matches("button:not(:focus-visible)", markup));
+        assert!(matches(".btn:hover:not(:focus)", markup));
+        assert!(matches(".btn:is(:disabled, :checked)::before", markup));
+        assert!(!matches(".missing:not(:hover)", markup));
+        assert!(!matches(".btn:hover:not(:hover)", markup));
+    }
+}
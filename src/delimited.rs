@@ -0,0 +1,186 @@
+use crate::audit::Audit;
+use crate::breakdown::Breakdown;
+use crate::dead_selectors::DeadSelectorReport;
+use crate::summary::{PropertyCount, Summary};
+use csv::WriterBuilder;
+
+/// Renders one row per custom property usage, separated by `delimiter`. Fields containing the
+/// delimiter, quotes or newlines are quoted.
+pub fn usages(audit: &Audit, delimiter: u8) -> String {
+    let mut rows: Vec<[String; 6]> = audit
+        .usages
+        .iter()
+        .map(|usage| {
+            [
+                usage.property.clone(),
+                usage.selector.clone(),
+                usage.context.join("; "),
+                usage.location.path.clone(),
+                usage.location.line.to_string(),
+                usage.location.column.to_string(),
+            ]
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        (&a[0], &a[3], a[4].parse::<u32>().ok(), &a[1]).cmp(&(
+            &b[0],
+            &b[3],
+            b[4].parse::<u32>().ok(),
+            &b[1],
+        ))
+    });
+    rows.dedup();
+
+    write(
+        delimiter,
+        ["property", "selector", "context", "file", "line", "column"],
+        rows,
+    )
+}
+
+/// Renders one row per custom property definition with its value, separated by `delimiter`.
+pub fn definitions(audit: &Audit, delimiter: u8) -> String {
+    let mut rows: Vec<[String; 7]> = audit
+        .definitions
+        .iter()
+        .map(|definition| {
+            [
+                definition.property.clone(),
+                definition.value.clone(),
+                definition.selector.clone(),
+                definition.context.join("; "),
+                definition.location.path.clone(),
+                definition.location.line.to_string(),
+                definition.location.column.to_string(),
+            ]
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        (&a[0], &a[4], a[5].parse::<u32>().ok(), &a[2]).cmp(&(
+            &b[0],
+            &b[4],
+            b[5].parse::<u32>().ok(),
+            &b[2],
+        ))
+    });
+    rows.dedup();
+
+    write(
+        delimiter,
+        [
+            "property", "value", "selector", "context", "file", "line", "column",
+        ],
+        rows,
+    )
+}
+
+/// Renders one row per selector with the custom properties it reads and defines, separated by
+/// `delimiter`. Lists of properties and locations are separated by spaces.
+pub fn selectors(audit: &Audit, delimiter: u8) -> String {
+    let rows = audit.selectors().into_iter().map(|selector| {
+        let locations: Vec<String> = selector
+            .locations
+            .iter()
+            .map(|location| location.to_string())
+            .collect();
+        [
+            selector.selector,
+            selector.context.join("; "),
+            Vec::from_iter(selector.reads).join(" "),
+            Vec::from_iter(selector.defines).join(" "),
+            locations.join(" "),
+        ]
+    });
+
+    write(
+        delimiter,
+        ["selector", "context", "reads", "defines", "locations"],
+        rows,
+    )
+}
+
+/// Renders one row per custom property read or defined in each file, with how many times it's
+/// read and defined there, separated by `delimiter`.
+pub fn files(breakdown: &Breakdown, delimiter: u8) -> String {
+    let rows = breakdown.files.iter().flat_map(|file| {
+        file.properties()
+            .into_iter()
+            .map(|(property, (reads, definitions))| {
+                [
+                    file.path.clone(),
+                    property.to_string(),
+                    reads.to_string(),
+                    definitions.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>()
+    });
+
+    write(
+        delimiter,
+        ["file", "property", "reads", "definitions"],
+        rows,
+    )
+}
+
+/// Renders the summary as one row per statistic, separated by `delimiter`. Lists of custom
+/// properties are separated by spaces, each followed by its usage count after a colon.
+pub fn summary(summary: &Summary, delimiter: u8) -> String {
+    let ranked = |properties: &[PropertyCount]| -> String {
+        let properties: Vec<String> = properties
+            .iter()
+            .map(|property| format!("{}:{}", property.name, property.usages))
+            .collect();
+        properties.join(" ")
+    };
+    let mut rows = vec![
+        ["properties".to_string(), summary.properties.to_string()],
+        ["usages".to_string(), summary.usages.to_string()],
+        ["definitions".to_string(), summary.definitions.to_string()],
+        ["most_used".to_string(), ranked(&summary.most_used)],
+        ["least_used".to_string(), ranked(&summary.least_used)],
+        ["single_file".to_string(), summary.single_file.join(" ")],
+    ];
+    for bucket in &summary.histogram {
+        rows.push([
+            format!("histogram {}", bucket.label()),
+            bucket.properties.to_string(),
+        ]);
+    }
+
+    write(delimiter, ["statistic", "value"], rows)
+}
+
+/// Renders one row per dead rule, separated by `delimiter`.
+pub fn dead_rules(report: &DeadSelectorReport, delimiter: u8) -> String {
+    let rows = report.rules.iter().map(|rule| {
+        [
+            rule.selector.clone(),
+            rule.custom_properties.join(" "),
+            rule.location.path.clone(),
+            rule.location.line.to_string(),
+            rule.location.column.to_string(),
+        ]
+    });
+
+    write(
+        delimiter,
+        ["selector", "custom_properties", "file", "line", "column"],
+        rows,
+    )
+}
+
+fn write<const N: usize>(
+    delimiter: u8,
+    header: [&str; N],
+    rows: impl IntoIterator<Item = [String; N]>,
+) -> String {
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    writer.write_record(header).unwrap();
+    for row in rows {
+        writer.write_record(&row).unwrap();
+    }
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
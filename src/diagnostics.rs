@@ -0,0 +1,121 @@
+use crate::findings::Severity;
+use crate::inputs::{CssBlock, SourceLocation};
+use lightningcss::error::{Error, ParserError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many lines to show before and after the line a diagnostic points at.
+const FRAME_CONTEXT_LINES: usize = 2;
+
+/// A problem reading or parsing an input file. Errors leave the file out of the audit, while
+/// warnings are about rules and declarations skipped when recovering from invalid CSS.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub path: String,
+    /// Where in the file the problem is, if it's known.
+    pub location: Option<SourceLocation>,
+    /// The lines around the location, with the column marked.
+    #[serde(skip)]
+    pub frame: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic for a whole file, such as one that couldn't be read.
+    pub fn file(severity: Severity, path: &str, message: String) -> Self {
+        Diagnostic {
+            severity,
+            message,
+            path: path.to_string(),
+            location: None,
+            frame: None,
+        }
+    }
+
+    /// A diagnostic pointing at a line and column of a file, showing the lines around it.
+    pub fn at(
+        severity: Severity,
+        location: SourceLocation,
+        contents: &str,
+        message: String,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            message,
+            path: location.path.clone(),
+            frame: Some(code_frame(contents, location.line, location.column)),
+            location: Some(location),
+        }
+    }
+
+    /// A diagnostic for an error or warning from parsing a block of CSS, pointing at where it is
+    /// in the file rather than in the block.
+    pub fn parse(
+        severity: Severity,
+        error: &Error<ParserError>,
+        block: &CssBlock,
+        path: &str,
+        contents: &str,
+    ) -> Self {
+        match &error.loc {
+            Some(loc) => Diagnostic::at(
+                severity,
+                block.location(path, loc.line, loc.column),
+                contents,
+                error.kind.to_string(),
+            ),
+            None => Diagnostic::file(severity, path, error.kind.to_string()),
+        }
+    }
+
+    /// The message along with where the problem is, in the form it's listed in reports.
+    pub fn label(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{}  ({})", self.message, location),
+            None => format!("{}  ({})", self.message, self.path),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity.name())?;
+        match &self.location {
+            Some(location) => write!(f, "{}\n  --> {}", self.message, location)?,
+            None => write!(f, "{}: {}", self.path, self.message)?,
+        }
+        if let Some(frame) = &self.frame {
+            write!(f, "\n{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+// the lines around a one-based line and column, numbered, with the line marked by > and the
+// column by ^
+fn code_frame(contents: &str, line: u32, column: u32) -> String {
+    let line = line as usize;
+    let lines: Vec<&str> = contents.lines().collect();
+    let first = line.saturating_sub(FRAME_CONTEXT_LINES).max(1);
+    let last = (line + FRAME_CONTEXT_LINES).min(lines.len());
+    let width = last.to_string().len();
+
+    let mut frame: Vec<String> = vec![];
+    for number in first..=last {
+        let text = lines[number - 1];
+        let marker = if number == line { '>' } else { ' ' };
+        let numbered = format!("{} {:>width$} | {}", marker, number, text, width = width);
+        frame.push(numbered.trim_end().to_string());
+        if number == line {
+            // line the caret up with the column, keeping any tabs so it lands in the same place
+            let padding: String = text
+                .chars()
+                .take(column.saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            frame.push(format!("  {:>width$} | {}^", "", padding, width = width));
+        }
+    }
+    frame.join("\n")
+}
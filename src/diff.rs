@@ -0,0 +1,214 @@
+use crate::audit::inline_label;
+use crate::patterns::NamePattern;
+use crate::schema::Report;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The differences between two JSON reports.
+#[derive(Serialize)]
+pub struct Diff {
+    /// Custom properties only in the new report.
+    pub added: Vec<String>,
+    /// Custom properties only in the old report.
+    pub removed: Vec<String>,
+    /// Custom properties in both reports that are used a different number of times.
+    pub usage_changes: Vec<UsageChange>,
+    /// Definitions in both reports, by the same selector, that give a different value.
+    pub value_changes: Vec<ValueChange>,
+    /// Selectors that read a deprecated custom property in the new report but not the old one.
+    pub deprecated_usages: Vec<DeprecatedUsage>,
+}
+
+#[derive(Serialize)]
+pub struct UsageChange {
+    pub name: String,
+    pub old: usize,
+    pub new: usize,
+}
+
+#[derive(Serialize)]
+pub struct ValueChange {
+    pub name: String,
+    /// The selector along with its context.
+    pub selector: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize)]
+pub struct DeprecatedUsage {
+    pub name: String,
+    /// The selector along with its context.
+    pub selector: String,
+    /// Where the selector reads the custom property in the new report, like `styles.css:3:1`.
+    pub location: String,
+}
+
+impl Diff {
+    /// Compares the custom properties of two reports in the schema_version 1 format. Custom
+    /// properties matching any of the `deprecated` patterns are reported when a selector starts
+    /// reading them.
+    pub fn new(old: &Report, new: &Report, deprecated: &[NamePattern]) -> Self {
+        let value_changes = value_changes(&definition_values(old), &definition_values(new));
+        let deprecated_usages = deprecated_usages(old, new, deprecated);
+        let old = usage_counts(old);
+        let new = usage_counts(new);
+
+        Diff {
+            added: new
+                .keys()
+                .filter(|name| !old.contains_key(*name))
+                .cloned()
+                .collect(),
+            removed: old
+                .keys()
+                .filter(|name| !new.contains_key(*name))
+                .cloned()
+                .collect(),
+            usage_changes: old
+                .iter()
+                .filter_map(|(name, &old)| match new.get(name) {
+                    Some(&new) if new != old => Some(UsageChange {
+                        name: name.clone(),
+                        old,
+                        new,
+                    }),
+                    _ => None,
+                })
+                .collect(),
+            value_changes,
+            deprecated_usages,
+        }
+    }
+
+    /// The differences as groups of lines, in the form they're listed in the terminal.
+    pub fn groups(&self) -> Vec<(String, Vec<String>)> {
+        vec![
+            ("Added".to_string(), self.added.clone()),
+            ("Removed".to_string(), self.removed.clone()),
+            (
+                "Usage changes".to_string(),
+                self.usage_changes
+                    .iter()
+                    .map(|change| format!("{}  ({} -> {})", change.name, change.old, change.new))
+                    .collect(),
+            ),
+            (
+                "Value changes".to_string(),
+                self.value_changes
+                    .iter()
+                    .map(|change| {
+                        format!(
+                            "{} in {}  ({} -> {})",
+                            change.name, change.selector, change.old, change.new
+                        )
+                    })
+                    .collect(),
+            ),
+            (
+                "New usages of deprecated custom properties".to_string(),
+                self.deprecated_usages
+                    .iter()
+                    .map(|usage| {
+                        format!("{} in {}  ({})", usage.name, usage.selector, usage.location)
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+// how many times each custom property in a report is used
+fn usage_counts(report: &Report) -> BTreeMap<String, usize> {
+    report
+        .properties
+        .iter()
+        .map(|property| (property.name.clone(), property.usages.len()))
+        .collect()
+}
+
+// the values each selector gives each custom property in a report, keyed by name and then
+// selector. A selector defining a property more than once has its values joined in order.
+fn definition_values(report: &Report) -> BTreeMap<(String, String), String> {
+    let mut values: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for property in &report.properties {
+        for definition in &property.definitions {
+            values
+                .entry((
+                    property.name.clone(),
+                    inline_label(&definition.context, &definition.selector),
+                ))
+                .or_default()
+                .push(definition.value.clone());
+        }
+    }
+    values
+        .into_iter()
+        .map(|(key, values)| (key, values.join(", ")))
+        .collect()
+}
+
+fn value_changes(
+    old: &BTreeMap<(String, String), String>,
+    new: &BTreeMap<(String, String), String>,
+) -> Vec<ValueChange> {
+    old.iter()
+        .filter_map(|(key, old)| match new.get(key) {
+            Some(new) if new != old => Some(ValueChange {
+                name: key.0.clone(),
+                selector: key.1.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// the usages of deprecated custom properties in the new report by selectors that didn't read
+// them in the old one
+fn deprecated_usages(
+    old: &Report,
+    new: &Report,
+    deprecated: &[NamePattern],
+) -> Vec<DeprecatedUsage> {
+    // each usage of a deprecated custom property, as its name, selector and location
+    let usages = |report: &Report| -> Vec<(String, String, String)> {
+        report
+            .properties
+            .iter()
+            .filter(|property| {
+                deprecated
+                    .iter()
+                    .any(|pattern| pattern.is_match(&property.name))
+            })
+            .flat_map(|property| {
+                property.usages.iter().map(|usage| {
+                    (
+                        property.name.clone(),
+                        inline_label(&usage.context, &usage.selector),
+                        usage.location.to_string(),
+                    )
+                })
+            })
+            .collect()
+    };
+
+    let before: BTreeSet<(String, String)> = usages(old)
+        .into_iter()
+        .map(|(name, selector, _)| (name, selector))
+        .collect();
+    let mut reported: BTreeSet<(String, String)> = BTreeSet::new();
+    usages(new)
+        .into_iter()
+        .filter(|(name, selector, _)| {
+            let key = (name.clone(), selector.clone());
+            !before.contains(&key) && reported.insert(key)
+        })
+        .map(|(name, selector, location)| DeprecatedUsage {
+            name,
+            selector,
+            location,
+        })
+        .collect()
+}
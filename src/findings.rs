@@ -0,0 +1,453 @@
+use crate::audit::{inline_label, Audit};
+use crate::inputs::SourceLocation;
+use crate::patterns::NamingPattern;
+use lightningcss::traits::Parse;
+use lightningcss::values::color::CssColor;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// How serious a finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// The severity as it's named in reports, which is also its SARIF level.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A check run against the audited custom properties.
+#[derive(Serialize)]
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+    /// The severity of the rule's findings unless it's configured, or `None` for rules that are
+    /// off unless they're turned on.
+    pub default_severity: Option<Severity>,
+}
+
+pub const UNDEFINED_CUSTOM_PROPERTY: Rule = Rule {
+    id: "undefined-custom-property",
+    description: "A custom property is used but never defined in the audited stylesheets.",
+    default_severity: Some(Severity::Warning),
+};
+
+pub const UNUSED_DEFINITION: Rule = Rule {
+    id: "unused-definition",
+    description: "A custom property is defined but never used in the audited stylesheets.",
+    default_severity: Some(Severity::Warning),
+};
+
+pub const MISSING_FALLBACK: Rule = Rule {
+    id: "missing-fallback",
+    description: "A custom property is read with var() without a fallback value.",
+    default_severity: None,
+};
+
+pub const FORBIDDEN_PREFIX: Rule = Rule {
+    id: "forbidden-prefix",
+    description: "A custom property's name starts with one of the forbidden prefixes.",
+    default_severity: Some(Severity::Error),
+};
+
+pub const NAMING_CONVENTION: Rule = Rule {
+    id: "naming-convention",
+    description: "A custom property's name doesn't follow the configured naming pattern.",
+    default_severity: Some(Severity::Warning),
+};
+
+pub const HARDCODED_VALUE: Rule = Rule {
+    id: "hardcoded-value",
+    description: "A declaration hardcodes a value that a custom property already defines.",
+    default_severity: Some(Severity::Note),
+};
+
+pub const HARDCODED_COLOR: Rule = Rule {
+    id: "hardcoded-color",
+    description: "A declaration hardcodes a color instead of reading it from a custom property.",
+    default_severity: None,
+};
+
+pub const DEAD_RULE: Rule = Rule {
+    id: "dead-rule",
+    description: "A style rule doesn't match any element in the markup fixtures.",
+    default_severity: Some(Severity::Warning),
+};
+
+/// Every rule, in the order they're listed in reports.
+pub const RULES: &[&Rule] = &[
+    &UNDEFINED_CUSTOM_PROPERTY,
+    &UNUSED_DEFINITION,
+    &MISSING_FALLBACK,
+    &FORBIDDEN_PREFIX,
+    &NAMING_CONVENTION,
+    &HARDCODED_VALUE,
+    &HARDCODED_COLOR,
+    &DEAD_RULE,
+];
+
+/// How the rules are configured for a run.
+#[derive(Default)]
+pub struct Config {
+    /// Severities that replace the rules' defaults, keyed by rule id. `None` turns a rule off.
+    pub severities: BTreeMap<String, Option<Severity>>,
+    /// The custom property name prefixes reported by the forbidden-prefix rule, like `--bs-`.
+    pub forbidden_prefixes: Vec<String>,
+    /// The naming convention checked by the naming-convention rule, if there is one.
+    pub naming: Option<NamingPattern>,
+}
+
+impl Config {
+    /// The severity of a rule's findings, or `None` if it's off.
+    pub fn severity(&self, rule: &Rule) -> Option<Severity> {
+        match self.severities.get(rule.id) {
+            Some(severity) => *severity,
+            None => rule.default_severity,
+        }
+    }
+}
+
+/// Parses a rule setting like `missing-fallback=error`, where the severity is `off`, `note`,
+/// `warning` or `error`.
+pub fn parse_rule_setting(setting: &str) -> Result<(String, Option<Severity>), String> {
+    let (id, severity) = setting
+        .split_once('=')
+        .ok_or_else(|| format!("expected RULE=SEVERITY, like {}=error", MISSING_FALLBACK.id))?;
+    if !RULES.iter().any(|rule| rule.id == id) {
+        let ids: Vec<&str> = RULES.iter().map(|rule| rule.id).collect();
+        return Err(format!(
+            "unknown rule '{}' [possible rules: {}]",
+            id,
+            ids.join(", ")
+        ));
+    }
+    let severity = match severity {
+        "off" => None,
+        "note" => Some(Severity::Note),
+        "warning" => Some(Severity::Warning),
+        "error" => Some(Severity::Error),
+        _ => {
+            return Err(format!(
+                "invalid severity '{}' [possible severities: off, note, warning, error]",
+                severity
+            ))
+        }
+    };
+    Ok((id.to_string(), severity))
+}
+
+/// A problem found by one of the rules.
+#[derive(Serialize)]
+pub struct Finding {
+    pub rule: &'static Rule,
+    pub severity: Severity,
+    pub message: String,
+    pub property: String,
+    /// The property of the declaration the finding is about, like `color`, for the rules that
+    /// check declarations rather than custom properties. Empty for the rest.
+    pub declaration: String,
+    pub selector: String,
+    pub location: SourceLocation,
+}
+
+/// Runs every rule that isn't off against the audit, returning the findings in source order.
+pub fn find(audit: &Audit, config: &Config) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+    let defined: HashSet<&str> = audit
+        .definitions
+        .iter()
+        .map(|definition| definition.property.as_str())
+        .collect();
+    let used: HashSet<&str> = audit
+        .usages
+        .iter()
+        .map(|usage| usage.property.as_str())
+        .collect();
+
+    // a selector can read the same custom property more than once in a rule, so only report
+    // each rule's usages once
+    let mut seen: HashSet<(&str, &str, &SourceLocation)> = HashSet::new();
+    let usages = audit
+        .usages
+        .iter()
+        .filter(|usage| seen.insert((&usage.property, &usage.selector, &usage.location)));
+    for usage in usages {
+        if let (false, Some(severity)) = (
+            defined.contains(usage.property.as_str()),
+            config.severity(&UNDEFINED_CUSTOM_PROPERTY),
+        ) {
+            findings.push(Finding {
+                rule: &UNDEFINED_CUSTOM_PROPERTY,
+                severity,
+                message: format!(
+                    "{} is used by {} but never defined",
+                    usage.property,
+                    inline_label(&usage.context, &usage.selector)
+                ),
+                property: usage.property.clone(),
+                declaration: String::new(),
+                selector: usage.selector.clone(),
+                location: usage.location.clone(),
+            });
+        }
+        // names are reported where they're defined, so only check the ones that aren't
+        if let (false, Some((naming, severity))) = (
+            defined.contains(usage.property.as_str()),
+            naming_convention(&usage.property, config),
+        ) {
+            findings.push(Finding {
+                rule: &NAMING_CONVENTION,
+                severity,
+                message: naming_message(
+                    &usage.property,
+                    "used",
+                    &inline_label(&usage.context, &usage.selector),
+                    naming,
+                ),
+                property: usage.property.clone(),
+                declaration: String::new(),
+                selector: usage.selector.clone(),
+                location: usage.location.clone(),
+            });
+        }
+        if let (false, Some(severity)) = (usage.has_fallback, config.severity(&MISSING_FALLBACK)) {
+            findings.push(Finding {
+                rule: &MISSING_FALLBACK,
+                severity,
+                message: format!(
+                    "{} is read by {} without a fallback value",
+                    usage.property,
+                    inline_label(&usage.context, &usage.selector)
+                ),
+                property: usage.property.clone(),
+                declaration: String::new(),
+                selector: usage.selector.clone(),
+                location: usage.location.clone(),
+            });
+        }
+        if let (Some(prefix), Some(severity)) = (
+            forbidden_prefix(&usage.property, config),
+            config.severity(&FORBIDDEN_PREFIX),
+        ) {
+            findings.push(Finding {
+                rule: &FORBIDDEN_PREFIX,
+                severity,
+                message: format!(
+                    "{} is used by {} but the {} prefix is forbidden",
+                    usage.property,
+                    inline_label(&usage.context, &usage.selector),
+                    prefix
+                ),
+                property: usage.property.clone(),
+                declaration: String::new(),
+                selector: usage.selector.clone(),
+                location: usage.location.clone(),
+            });
+        }
+    }
+
+    for definition in &audit.definitions {
+        if let (false, Some(severity)) = (
+            used.contains(definition.property.as_str()),
+            config.severity(&UNUSED_DEFINITION),
+        ) {
+            findings.push(Finding {
+                rule: &UNUSED_DEFINITION,
+                severity,
+                message: format!(
+                    "{} is defined by {} but never used",
+                    definition.property,
+                    inline_label(&definition.context, &definition.selector)
+                ),
+                property: definition.property.clone(),
+                declaration: String::new(),
+                selector: definition.selector.clone(),
+                location: definition.location.clone(),
+            });
+        }
+        if let (Some(prefix), Some(severity)) = (
+            forbidden_prefix(&definition.property, config),
+            config.severity(&FORBIDDEN_PREFIX),
+        ) {
+            findings.push(Finding {
+                rule: &FORBIDDEN_PREFIX,
+                severity,
+                message: format!(
+                    "{} is defined by {} but the {} prefix is forbidden",
+                    definition.property,
+                    inline_label(&definition.context, &definition.selector),
+                    prefix
+                ),
+                property: definition.property.clone(),
+                declaration: String::new(),
+                selector: definition.selector.clone(),
+                location: definition.location.clone(),
+            });
+        }
+        if let Some((naming, severity)) = naming_convention(&definition.property, config) {
+            findings.push(Finding {
+                rule: &NAMING_CONVENTION,
+                severity,
+                message: naming_message(
+                    &definition.property,
+                    "defined",
+                    &inline_label(&definition.context, &definition.selector),
+                    naming,
+                ),
+                property: definition.property.clone(),
+                declaration: String::new(),
+                selector: definition.selector.clone(),
+                location: definition.location.clone(),
+            });
+        }
+    }
+
+    // map each value worth tokenizing to the custom properties that define it
+    let mut tokens: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for definition in &audit.definitions {
+        if is_token_value(&definition.value) {
+            let properties = tokens.entry(&definition.value).or_default();
+            if !properties.contains(&definition.property.as_str()) {
+                properties.push(&definition.property);
+            }
+        }
+    }
+    for declaration in &audit.declarations {
+        let hardcoded_value = tokens.get(declaration.value.as_str());
+        if let (Some(properties), Some(severity)) =
+            (hardcoded_value, config.severity(&HARDCODED_VALUE))
+        {
+            let suggestions: Vec<String> = properties
+                .iter()
+                .map(|property| format!("var({})", property))
+                .collect();
+            findings.push(Finding {
+                rule: &HARDCODED_VALUE,
+                severity,
+                message: format!(
+                    "{}: {} in {} hardcodes a token value; use {} instead",
+                    declaration.property,
+                    declaration.value,
+                    inline_label(&declaration.context, &declaration.selector),
+                    suggestions.join(" or ")
+                ),
+                property: properties[0].to_string(),
+                declaration: declaration.property.clone(),
+                selector: declaration.selector.clone(),
+                location: declaration.location.clone(),
+            });
+            // the finding above already suggests the custom property to use for any color
+            continue;
+        }
+        if let (Some(color), Some(severity)) = (
+            hardcoded_color(&declaration.value),
+            config.severity(&HARDCODED_COLOR),
+        ) {
+            findings.push(Finding {
+                rule: &HARDCODED_COLOR,
+                severity,
+                message: format!(
+                    "{}: {} in {} hardcodes the color {}; read it from a custom property instead",
+                    declaration.property,
+                    declaration.value,
+                    inline_label(&declaration.context, &declaration.selector),
+                    color
+                ),
+                property: String::new(),
+                declaration: declaration.property.clone(),
+                selector: declaration.selector.clone(),
+                location: declaration.location.clone(),
+            });
+        }
+    }
+
+    findings.sort_by(|a, b| {
+        a.location
+            .cmp(&b.location)
+            .then_with(|| a.rule.id.cmp(b.rule.id))
+            .then_with(|| a.property.cmp(&b.property))
+    });
+    findings
+}
+
+// the forbidden prefix a custom property's name starts with, if any
+fn forbidden_prefix<'a>(property: &str, config: &'a Config) -> Option<&'a str> {
+    config
+        .forbidden_prefixes
+        .iter()
+        .find(|prefix| property.starts_with(prefix.as_str()))
+        .map(String::as_str)
+}
+
+// the naming convention a custom property's name breaks, and the severity to report it with
+fn naming_convention<'a>(
+    property: &str,
+    config: &'a Config,
+) -> Option<(&'a NamingPattern, Severity)> {
+    let naming = config.naming.as_ref()?;
+    let severity = config.severity(&NAMING_CONVENTION)?;
+    (!naming.is_match(property)).then_some((naming, severity))
+}
+
+fn naming_message(property: &str, verb: &str, label: &str, naming: &NamingPattern) -> String {
+    let message = format!(
+        "{} is {} by {} but doesn't follow the naming pattern {}",
+        property,
+        verb,
+        label,
+        naming.describe()
+    );
+    match naming.suggestion(property) {
+        Some(suggestion) => format!("{}; rename it to {}", message, suggestion),
+        None => message,
+    }
+}
+
+// the first color written out in a value, like the #fff in `1px solid #fff`. Values are split
+// into words at the top level, so functions like rgb() are kept whole. Keywords that aren't
+// really colors, like currentcolor and transparent, are left out.
+fn hardcoded_color(value: &str) -> Option<&str> {
+    let mut words: Vec<&str> = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ' ' | ',' | '/' if depth == 0 => {
+                words.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    words.push(&value[start..]);
+
+    words.into_iter().find(|word| {
+        !word.is_empty()
+            && !matches!(
+                word.to_ascii_lowercase().as_str(),
+                "currentcolor" | "transparent" | "inherit" | "initial" | "unset" | "revert"
+            )
+            && CssColor::parse_string(word).is_ok()
+    })
+}
+
+// keywords like `none` or `block`, and zero, are too common to be worth flagging when they're
+// written out instead of read from a custom property. Named colors are the exception, since
+// lightningcss prints colors like #ff0000 by name.
+fn is_token_value(value: &str) -> bool {
+    let is_keyword = value.chars().all(|c| c.is_ascii_alphabetic() || c == '-');
+    let is_color = value != "currentcolor" && CssColor::parse_string(value).is_ok();
+    !value.is_empty() && (!is_keyword || is_color) && value != "0"
+}
@@ -0,0 +1,116 @@
+use crate::audit::Audit;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Which custom properties are defined in terms of others, like `--button-bg: var(--blue)`.
+#[derive(Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Serialize)]
+pub struct Node {
+    pub name: String,
+    /// Whether the custom property is defined in the audited stylesheets. Undefined ones are
+    /// only referenced by the values of others.
+    pub defined: bool,
+}
+
+/// A custom property whose value reads another.
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+}
+
+impl Graph {
+    pub fn new(audit: &Audit) -> Self {
+        let mut nodes: BTreeMap<&str, bool> = BTreeMap::new();
+        let mut edges: BTreeSet<Edge> = BTreeSet::new();
+        for definition in &audit.definitions {
+            nodes.insert(&definition.property, true);
+            for reference in &definition.references {
+                nodes.entry(reference).or_insert(false);
+                edges.insert(Edge {
+                    from: definition.property.clone(),
+                    to: reference.clone(),
+                });
+            }
+        }
+
+        Graph {
+            nodes: nodes
+                .into_iter()
+                .map(|(name, defined)| Node {
+                    name: name.to_string(),
+                    defined,
+                })
+                .collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// Renders the graph in Graphviz's DOT language, with undefined custom properties dashed.
+    pub fn dot(&self) -> String {
+        let mut dot = String::from("digraph custom_properties {\n    rankdir=LR;\n");
+        dot.push_str("    node [shape=box, fontname=monospace];\n");
+        for node in &self.nodes {
+            match node.defined {
+                true => dot.push_str(&format!("    {};\n", quote(&node.name))),
+                false => dot.push_str(&format!("    {} [style=dashed];\n", quote(&node.name))),
+            }
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    {} -> {};\n",
+                quote(&edge.from),
+                quote(&edge.to)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart, with undefined custom properties dashed.
+    pub fn mermaid(&self) -> String {
+        // custom property names aren't valid Mermaid ids, so number the nodes and label them
+        let ids: BTreeMap<&str, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.name.as_str(), format!("n{}", index)))
+            .collect();
+        let mut mermaid = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            mermaid.push_str(&format!(
+                "    {}[\"{}\"]\n",
+                ids[node.name.as_str()],
+                node.name.replace('"', "#quot;")
+            ));
+        }
+        for edge in &self.edges {
+            mermaid.push_str(&format!(
+                "    {} --> {}\n",
+                ids[edge.from.as_str()],
+                ids[edge.to.as_str()]
+            ));
+        }
+        let undefined: Vec<&str> = self
+            .nodes
+            .iter()
+            .filter(|node| !node.defined)
+            .map(|node| ids[node.name.as_str()].as_str())
+            .collect();
+        if !undefined.is_empty() {
+            mermaid.push_str("    classDef undefined stroke-dasharray: 5 5\n");
+            mermaid.push_str(&format!("    class {} undefined\n", undefined.join(",")));
+        }
+        mermaid
+    }
+}
+
+// DOT ids with anything other than letters, digits and underscores have to be quoted
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,344 @@
+use crate::audit::{Audit, Definition, Usage};
+use crate::breakdown::{Breakdown, Footprint};
+use crate::summary::{list, PropertyCount, Summary};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The page the HTML report is rendered into.
+const REPORT_TEMPLATE: &str = include_str!("report/report.html");
+
+/// The heading and list rendered for each group.
+const SECTION_TEMPLATE: &str = r#"<section class="group" data-kind="{{ kind }}"><h2 id="{{ id }}"><span class="label">{{ heading }}</span> <span class="count">({{ count }})</span></h2><ul>{{ items }}</ul></section>"#;
+
+const ITEM_TEMPLATE: &str = r#"<li><span class="entry">{{ text }}</span></li>"#;
+
+/// The summary shown above the groups.
+const SUMMARY_TEMPLATE: &str = r#"<section class="summary"><h2 id="summary">Summary</h2><dl><div><dt>Custom properties</dt><dd>{{ properties }}</dd></div><div><dt>Usages</dt><dd>{{ usages }}</dd></div><div><dt>Definitions</dt><dd>{{ definitions }}</dd></div><div><dt>Most used</dt><dd>{{ most_used }}</dd></div><div><dt>Least used</dt><dd>{{ least_used }}</dd></div><div><dt>Used in only one file</dt><dd>{{ single_file }}</dd></div></dl><table><caption>Custom properties by number of usages</caption><thead><tr><th scope="col">Usages</th><th scope="col">Custom properties</th><td></td></tr></thead><tbody>{{ histogram }}</tbody></table></section>"#;
+
+const HISTOGRAM_ROW_TEMPLATE: &str = r#"<tr><th scope="row">{{ label }}</th><td>{{ count }}</td><td><meter min="0" max="{{ max }}" value="{{ count }}"></meter></td></tr>"#;
+
+/// How many lines of source to show either side of a usage.
+const SNIPPET_CONTEXT_LINES: usize = 2;
+
+const MINIMAP_LINK_TEMPLATE: &str = r##"<a href="#{{ id }}">{{ text }}</a>"##;
+
+/// Markup that's safe to insert into a document as-is. Text only becomes `Html` by being
+/// escaped, or by being one of the report's own templates, stylesheets or scripts.
+#[derive(Default)]
+pub struct Html(String);
+
+impl Html {
+    /// Escapes text so it displays literally, whatever characters it contains.
+    pub fn text(text: &str) -> Html {
+        Html(escape(text))
+    }
+
+    /// Escapes text like `text`, keeping its line breaks.
+    pub fn lines(text: &str) -> Html {
+        let lines: Vec<String> = text.lines().map(escape).collect();
+        Html(lines.join("\n<br>"))
+    }
+
+    /// Serializes a value as JSON that can be embedded in a `<script>` element. `<` only appears
+    /// inside strings, where it can be escaped so the data never closes the element.
+    fn json(value: &impl Serialize) -> Html {
+        let json = serde_json::to_string(value).expect("Failed to serialize report data");
+        Html(json.replace('<', "\\u003c"))
+    }
+
+    /// Markup that's part of the report itself, never anything read from the audited files.
+    fn trusted(markup: &'static str) -> Html {
+        Html(markup.to_string())
+    }
+
+    pub fn push(&mut self, html: Html) {
+        self.0.push_str(&html.0);
+    }
+}
+
+impl fmt::Display for Html {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Escapes the characters that are special in HTML text and quoted attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fills the `{{ name }}` slots of a template. The template is split up before anything is
+/// inserted, so values are never searched for slots themselves.
+fn render(template: &'static str, slots: &[(&str, &Html)]) -> Html {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open..].find("}}").map(|index| open + index) else {
+            break;
+        };
+        let name = rest[open + 2..close].trim();
+        let value = slots
+            .iter()
+            .find(|(slot, _)| *slot == name)
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| panic!("Failed to render template: missing slot {}", name));
+        rendered.push_str(&rest[..open]);
+        rendered.push_str(&value.0);
+        rest = &rest[close + 2..];
+    }
+    rendered.push_str(rest);
+    Html(rendered)
+}
+
+/// The data the report's filters and views are built from.
+#[derive(Serialize)]
+struct ReportData<'a> {
+    /// The view shown first, `properties`, `selectors` or `files`.
+    view: &'a str,
+    /// The totals of each directory and file, used to filter by them.
+    directories: &'a [Footprint],
+    files: &'a [Footprint],
+    usages: Vec<PropertyData<'a, Usage>>,
+    definitions: Vec<PropertyData<'a, Definition>>,
+    /// The lines around each usage and definition, keyed by `path:line`.
+    snippets: BTreeMap<String, Snippet<'a>>,
+}
+
+/// A usage or definition along with the custom property it's for.
+#[derive(Serialize)]
+struct PropertyData<'a, T> {
+    property: &'a str,
+    #[serde(flatten)]
+    data: &'a T,
+}
+
+#[derive(Serialize)]
+struct Snippet<'a> {
+    /// The line number of the first line.
+    start: usize,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> ReportData<'a> {
+    fn new(report: &Report<'a>) -> Self {
+        let Report {
+            audit,
+            breakdown,
+            sources,
+            view,
+            ..
+        } = *report;
+        let locations = audit.usages.iter().map(|usage| &usage.location).chain(
+            audit
+                .definitions
+                .iter()
+                .map(|definition| &definition.location),
+        );
+        let mut snippets: BTreeMap<String, Snippet> = BTreeMap::new();
+        for location in locations {
+            let Some(source) = sources.get(&location.path) else {
+                continue;
+            };
+            let line = location.line as usize;
+            let start = line.saturating_sub(SNIPPET_CONTEXT_LINES).max(1);
+            snippets
+                .entry(format!("{}:{}", location.path, line))
+                .or_insert_with(|| Snippet {
+                    start,
+                    lines: source
+                        .lines()
+                        .skip(start - 1)
+                        .take(line + SNIPPET_CONTEXT_LINES + 1 - start)
+                        .collect(),
+                });
+        }
+
+        ReportData {
+            view,
+            directories: &breakdown.directories,
+            files: &breakdown.files,
+            usages: audit
+                .usages
+                .iter()
+                .map(|usage| PropertyData {
+                    property: &usage.property,
+                    data: usage,
+                })
+                .collect(),
+            definitions: audit
+                .definitions
+                .iter()
+                .map(|definition| PropertyData {
+                    property: &definition.property,
+                    data: definition,
+                })
+                .collect(),
+            snippets,
+        }
+    }
+}
+
+/// Everything shown in the HTML report.
+pub struct Report<'a> {
+    /// Groups listing the custom properties used, for readers without JavaScript. They're
+    /// replaced by the interactive views built from the `audit` when it runs.
+    pub usage_groups: &'a [(String, Vec<String>)],
+    /// Any other groups, always shown as rendered.
+    pub other_groups: &'a [(String, Vec<String>)],
+    /// The usages and definitions the interactive views are built from.
+    pub audit: &'a Audit,
+    /// The totals of each directory and file, used to filter by them.
+    pub breakdown: &'a Breakdown,
+    /// The contents of each file, keyed by path, to show snippets of around each usage.
+    pub sources: &'a BTreeMap<String, String>,
+    /// The view shown first, `properties`, `selectors` or `files`.
+    pub view: &'a str,
+    pub summary: Option<&'a Summary>,
+}
+
+/// Renders each group as a heading and a list of its entries, with a minimap of links to the
+/// headings and the summary above them. A hash of the group name is used as the heading's id.
+pub fn render_report(report: &Report) -> String {
+    let mut sections = Html::default();
+    let mut minimap = Html::default();
+    let groups = report
+        .usage_groups
+        .iter()
+        .map(|group| ("usages", group))
+        .chain(report.other_groups.iter().map(|group| ("other", group)));
+    for (kind, (key, value)) in groups {
+        let id = Html::text(&format!("selector-{:x}", xxh3_64(key.as_bytes())));
+        let mut items = Html::default();
+        for entry in value {
+            items.push(render(ITEM_TEMPLATE, &[("text", &Html::lines(entry))]));
+        }
+        sections.push(render(
+            SECTION_TEMPLATE,
+            &[
+                ("kind", &Html::trusted(kind)),
+                ("id", &id),
+                ("heading", &Html::lines(key)),
+                ("count", &Html::text(&value.len().to_string())),
+                ("items", &items),
+            ],
+        ));
+        minimap.push(render(
+            MINIMAP_LINK_TEMPLATE,
+            &[("id", &id), ("text", &Html::lines(key))],
+        ));
+    }
+
+    render(
+        REPORT_TEMPLATE,
+        &[
+            ("style", &Html::trusted(include_str!("report/report.css"))),
+            ("script", &Html::trusted(include_str!("report/report.js"))),
+            ("minimap", &minimap),
+            (
+                "summary",
+                &report.summary.map(render_summary).unwrap_or_default(),
+            ),
+            ("sections", &sections),
+            ("data", &Html::json(&ReportData::new(report))),
+        ],
+    )
+    .to_string()
+}
+
+fn render_summary(summary: &Summary) -> Html {
+    let ranked = |properties: &[PropertyCount]| -> Html {
+        let properties: Vec<String> = properties
+            .iter()
+            .map(|property| format!("{} ({})", property.name, property.usages))
+            .collect();
+        Html::text(&list(&properties))
+    };
+    let count = |count: usize| Html::text(&count.to_string());
+
+    let largest = summary
+        .histogram
+        .iter()
+        .map(|bucket| bucket.properties)
+        .max()
+        .unwrap_or(0);
+    let mut histogram = Html::default();
+    for bucket in &summary.histogram {
+        histogram.push(render(
+            HISTOGRAM_ROW_TEMPLATE,
+            &[
+                ("label", &Html::text(&bucket.label())),
+                ("count", &count(bucket.properties)),
+                ("max", &count(largest)),
+            ],
+        ));
+    }
+
+    render(
+        SUMMARY_TEMPLATE,
+        &[
+            ("properties", &count(summary.properties)),
+            ("usages", &count(summary.usages)),
+            ("definitions", &count(summary.definitions)),
+            ("most_used", &ranked(&summary.most_used)),
+            ("least_used", &ranked(&summary.least_used)),
+            ("single_file", &Html::text(&list(&summary.single_file))),
+            ("histogram", &histogram),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text_from_the_audited_files() {
+        let selector = r#"a[title="<b>"] {{ x }}"#;
+        assert_eq!(
+            Html::text(selector).to_string(),
+            "a[title=&quot;&lt;b&gt;&quot;] {{ x }}"
+        );
+        assert_eq!(
+            Html::json(&["</script>"]).to_string(),
+            r#"["\u003c/script>"]"#
+        );
+    }
+
+    #[test]
+    fn never_renders_slots_in_inserted_values() {
+        // the selector's {{ x }} isn't a slot of either template, so rendering would panic if
+        // it were searched for slots
+        let item = render(ITEM_TEMPLATE, &[("text", &Html::text("{{ x }} <i>"))]);
+        assert_eq!(
+            item.to_string(),
+            r#"<li><span class="entry">{{ x }} &lt;i&gt;</span></li>"#
+        );
+        let section = render(
+            SECTION_TEMPLATE,
+            &[
+                ("kind", &Html::trusted("property")),
+                ("id", &Html::text("x")),
+                ("heading", &Html::text("{{ items }}")),
+                ("count", &Html::text("1")),
+                ("items", &item),
+            ],
+        );
+        assert!(section
+            .to_string()
+            .contains(r#"<span class="label">{{ items }}</span>"#));
+        assert!(section.to_string().contains("<ul><li>"));
+    }
+}
@@ -0,0 +1,726 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The extensions of files picked up when walking a directory or expanding a glob.
+const AUDITED_EXTENSIONS: &[&str] = &[
+    "css", "vue", "svelte", "js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts",
+];
+
+/// The text of the comment that leaves the declaration following it out of the audit.
+const IGNORE_COMMENT: &str = "css-audit-ignore";
+
+/// A block of CSS pulled out of an input file.
+pub struct CssBlock {
+    pub css: String,
+    /// The zero-based line in the input file that the block starts on.
+    pub line_offset: u32,
+    /// The zero-based column in the input file that the block's first line starts on.
+    pub column_offset: u32,
+    /// The length of any text added to the start of the block that isn't in the input file,
+    /// like the rule wrapped around the declarations in a tagged template.
+    pub prefix_width: u32,
+    /// Whether the block is a CSS module, i.e. a `*.module.css` file or a `<style module>` block.
+    pub css_modules: bool,
+    /// The zero-based lines and one-based columns in the block of any css-audit-ignore comments
+    /// that aren't followed by a declaration, so have no effect.
+    pub unused_ignore_comments: Vec<(u32, u32)>,
+}
+
+impl CssBlock {
+    /// Converts a zero-based line and one-based column within the block into a location in
+    /// the input file.
+    pub fn location(&self, path: &str, line: u32, column: u32) -> SourceLocation {
+        SourceLocation {
+            path: path.to_string(),
+            line: self.line_offset + line + 1,
+            column: if line == 0 {
+                // anything in the added text is put at the start of the block
+                self.column_offset + column.saturating_sub(self.prefix_width).max(1)
+            } else {
+                column
+            },
+        }
+    }
+}
+
+/// Where a rule was found in an input file. Lines and columns start at 1.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub path: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+/// Extracts the CSS from an input file based on its extension. `<style>` blocks are pulled out
+/// of Vue and Svelte components, `css` tagged template literals out of JavaScript and
+/// TypeScript, and anything else is treated as a stylesheet.
+pub fn extract(path: &str, contents: &str) -> Vec<CssBlock> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    let mut blocks = match extension {
+        "vue" | "svelte" => extract_style_elements(path, contents),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => {
+            extract_tagged_templates(path, contents)
+        }
+        _ => vec![CssBlock {
+            css: contents.to_string(),
+            line_offset: 0,
+            column_offset: 0,
+            prefix_width: 0,
+            css_modules: path.ends_with(".module.css"),
+            unused_ignore_comments: vec![],
+        }],
+    };
+    for block in &mut blocks {
+        if block.css.contains(IGNORE_COMMENT) {
+            (block.css, block.unused_ignore_comments) = blank_ignored_declarations(&block.css);
+        }
+    }
+    blocks
+}
+
+// blank out each declaration that follows a /* css-audit-ignore */ comment, so lightningcss
+// never sees it. lightningcss drops comments, so this has to happen before parsing. Newlines
+// are kept and every other character becomes spaces, so the locations of everything else stay
+// the same. Also returns the zero-based line and one-based column of each ignore comment that
+// isn't followed by a declaration, like one before a selector.
+fn blank_ignored_declarations(css: &str) -> (String, Vec<(u32, u32)>) {
+    let mut blanked = String::with_capacity(css.len());
+    let mut unused: Vec<(u32, u32)> = vec![];
+    // where the ignore comment waiting for a declaration is, if there is one
+    let mut ignoring: Option<(u32, u32)> = None;
+    let mut rest = css;
+    while let Some(c) = rest.chars().next() {
+        if let Some(comment) = rest.strip_prefix("/*") {
+            let (text, end) = match comment.find("*/") {
+                Some(index) => (&comment[..index], index + 4),
+                None => (comment, rest.len()),
+            };
+            if text.trim() == IGNORE_COMMENT {
+                unused.extend(ignoring.take());
+                ignoring = Some(position(&blanked));
+            }
+            blanked.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if let Some(index) = ignoring.filter(|_| !c.is_whitespace()) {
+            ignoring = None;
+            let end = declaration_end(rest);
+            // a selector or at-rule follows the comment rather than a declaration, or the block
+            // ends right after it
+            if end == 0 || rest[..end].ends_with('{') {
+                unused.push(index);
+                blanked.push_str(&rest[..end]);
+            } else {
+                for c in rest[..end].chars() {
+                    match c {
+                        '\n' => blanked.push('\n'),
+                        c => blanked.extend(std::iter::repeat_n(' ', c.len_utf16())),
+                    }
+                }
+            }
+            rest = &rest[end..];
+        } else {
+            // copy strings and unquoted URLs whole, since comments can't start inside them
+            let end = match c {
+                '"' | '\'' => string_end(rest),
+                _ if unquoted_url(rest) => rest.find(')').map_or(rest.len(), |index| index + 1),
+                _ => c.len_utf8(),
+            };
+            blanked.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    unused.extend(ignoring);
+    (blanked, unused)
+}
+
+// the zero-based line and one-based column, counted in UTF-16 code units like lightningcss
+// does, of the end of `text`
+fn position(text: &str) -> (u32, u32) {
+    let line_start = text.rfind('\n').map_or(0, |index| index + 1);
+    (
+        text.matches('\n').count() as u32,
+        text[line_start..].encode_utf16().count() as u32 + 1,
+    )
+}
+
+// the length of the quoted string at the start of `css`, including its quotes. Strings end at
+// an unescaped newline too, like they do in CSS.
+fn string_end(css: &str) -> usize {
+    let mut chars = css.char_indices();
+    let Some((_, quote)) = chars.next() else {
+        return 0;
+    };
+    let mut escaped = false;
+    for (index, c) in chars {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' => return index,
+            c if c == quote => return index + c.len_utf8(),
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+// whether `css` starts with url( and an unquoted URL, which can contain /* as it is
+fn unquoted_url(css: &str) -> bool {
+    css.get(..4)
+        .is_some_and(|start| start.eq_ignore_ascii_case("url("))
+        && !css[4..].trim_start().starts_with(['"', '\''])
+}
+
+// the length of the declaration at the start of `css`, up to and including its semicolon, or
+// up to the closing brace of its block. Anything in strings or parentheses is skipped, like
+// the semicolon in a data URL.
+fn declaration_end(css: &str) -> usize {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (index, c) in css.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (_, '\\') => escaped = true,
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ';' | '{') if depth <= 0 => return index + 1,
+            (None, '}') if depth <= 0 => return index,
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+fn block_at(contents: &str, start: usize, css: String, css_modules: bool) -> CssBlock {
+    let before = &contents[..start];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    CssBlock {
+        css,
+        line_offset: before.matches('\n').count() as u32,
+        column_offset: before[line_start..].encode_utf16().count() as u32,
+        prefix_width: 0,
+        css_modules,
+        unused_ignore_comments: vec![],
+    }
+}
+
+// pull out the contents of every <style> element, skipping any written in a preprocessor
+// language that lightningcss can't parse
+fn extract_style_elements(path: &str, contents: &str) -> Vec<CssBlock> {
+    let lowercase = contents.to_ascii_lowercase();
+    let mut blocks: Vec<CssBlock> = vec![];
+    let mut cursor = 0;
+
+    while let Some(open) = lowercase[cursor..]
+        .find("<style")
+        .map(|index| cursor + index)
+    {
+        let Some(start) = lowercase[open..].find('>').map(|index| open + index + 1) else {
+            break;
+        };
+        let end = lowercase[start..]
+            .find("</style")
+            .map_or(contents.len(), |index| start + index);
+        cursor = end;
+
+        let tag = &lowercase[open..start];
+        let lang = ["lang=\"", "lang='"]
+            .iter()
+            .find_map(|attr| tag.find(attr).map(|index| &tag[index + attr.len()..]))
+            .and_then(|rest| rest.split(['"', '\'']).next());
+        match lang {
+            None | Some("css") | Some("postcss") => {
+                let css_modules = tag
+                    .trim_end_matches(['>', '/'])
+                    .split_whitespace()
+                    .any(|attr| attr == "module" || attr.starts_with("module="));
+                blocks.push(block_at(
+                    contents,
+                    start,
+                    contents[start..end].to_string(),
+                    css_modules,
+                ));
+            }
+            Some(lang) => {
+                eprintln!("Skipping <style lang=\"{}\"> in {}", lang, path);
+            }
+        }
+    }
+
+    blocks
+}
+
+// pull out the contents of every css`...` tagged template literal, replacing interpolations
+// with a placeholder identifier so the CSS still parses and line numbers still line up
+fn extract_tagged_templates(path: &str, contents: &str) -> Vec<CssBlock> {
+    let bytes = contents.as_bytes();
+    let mut blocks: Vec<CssBlock> = vec![];
+    let mut cursor = 0;
+
+    while let Some(tag) = contents[cursor..].find("css").map(|index| cursor + index) {
+        cursor = tag + 3;
+        let is_identifier = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$';
+        if tag > 0 && (is_identifier(bytes[tag - 1]) || bytes[tag - 1] == b'.') {
+            continue;
+        }
+        let Some(backtick) = contents[cursor..]
+            .char_indices()
+            .find(|(_, c)| !c.is_whitespace())
+            .filter(|(_, c)| *c == '`')
+            .map(|(index, _)| cursor + index)
+        else {
+            continue;
+        };
+
+        let start = backtick + 1;
+        let mut css = String::new();
+        let mut index = start;
+        while index < bytes.len() && bytes[index] != b'`' {
+            if bytes[index] == b'\\' && index + 1 < bytes.len() {
+                let next = next_char(contents, index + 1);
+                css.push_str(&contents[index..next]);
+                index = next;
+            } else if bytes[index] == b'$' && bytes.get(index + 1) == Some(&b'{') {
+                let end = interpolation_end(bytes, index + 2);
+                css.push_str(INTERPOLATION_PLACEHOLDER);
+                for _ in contents[index..end].matches('\n') {
+                    css.push('\n');
+                }
+                index = end;
+            } else {
+                let next = next_char(contents, index);
+                css.push_str(&contents[index..next]);
+                index = next;
+            }
+        }
+
+        // a file that's being edited can end in the middle of a template
+        if index >= bytes.len() {
+            eprintln!("Skipping an unterminated css` template in {}", path);
+            break;
+        }
+
+        let mut block = block_at(contents, start, css, false);
+        // styled-components and emotion templates are usually just declarations, so wrap
+        // those in a rule, leaving the rule out of the block's locations
+        if !starts_with_rule(&block.css) {
+            block.css = format!("{}{{{}}}", TEMPLATE_SELECTOR, block.css);
+            block.prefix_width = TEMPLATE_SELECTOR.len() as u32 + 1;
+        }
+        blocks.push(block);
+        cursor = index + 1;
+    }
+
+    blocks
+}
+
+/// The selector declarations in a tagged template are audited under, which is the element the
+/// template's styles are applied to.
+const TEMPLATE_SELECTOR: &str = "&";
+
+// whether the CSS starts with a rule or at-rule rather than a declaration, i.e. a { comes before
+// any ; or }. CSS that's empty apart from comments counts as rules, since there's nothing to wrap.
+fn starts_with_rule(css: &str) -> bool {
+    let mut rest = css;
+    let mut empty = true;
+    while let Some(c) = rest.chars().next() {
+        let end = match c {
+            '{' => return true,
+            ';' | '}' => return false,
+            '/' if rest.starts_with("/*") => rest.find("*/").map_or(rest.len(), |index| index + 2),
+            '"' | '\'' => string_end(rest),
+            c => {
+                empty &= c.is_whitespace();
+                c.len_utf8()
+            }
+        };
+        rest = &rest[end..];
+    }
+    empty
+}
+
+// the byte index of the character after the one starting at `index`
+fn next_char(contents: &str, index: usize) -> usize {
+    contents[index..]
+        .chars()
+        .next()
+        .map_or(contents.len(), |c| index + c.len_utf8())
+}
+
+const INTERPOLATION_PLACEHOLDER: &str = "__css_audit_interpolation";
+
+// find the end of a ${...} interpolation, allowing for nested braces and strings
+fn interpolation_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 1;
+    let mut quote: Option<u8> = None;
+    let mut index = start;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        match quote {
+            Some(_) if byte == b'\\' => index += 1,
+            Some(q) if byte == q => quote = None,
+            Some(_) => {}
+            None => match byte {
+                b'"' | b'\'' | b'`' => quote = Some(byte),
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return index + 1;
+                    }
+                }
+                _ => {}
+            },
+        }
+        index += 1;
+    }
+    bytes.len()
+}
+
+/// Expands the paths given on the command line into the files to audit. Directories are walked
+/// recursively and glob patterns like `src/**/*.css` are matched, both skipping anything
+/// ignored by `.gitignore` files. Files listed explicitly are kept whatever their extension.
+/// Paths matching any of the `excludes` are left out. Fails on a glob pattern that isn't valid.
+pub fn expand_paths(args: &[String], excludes: &[ExcludePattern]) -> Result<Vec<String>, String> {
+    let mut paths: Vec<String> = vec![];
+
+    for arg in args {
+        // files and directories like [slug] that exist are taken as they are, not as patterns
+        if is_glob(arg) && !Path::new(arg).exists() {
+            let glob = Glob::new(arg.trim_start_matches("./"))
+                .map_err(|error| format!("Invalid glob pattern {}: {}", arg, error.kind()))?
+                .compile_matcher();
+            let base = glob_base(arg);
+            paths.extend(
+                walk(&base, excludes)
+                    .into_iter()
+                    .filter(|path| glob.is_match(path.trim_start_matches("./"))),
+            );
+        } else if Path::new(arg).is_dir() {
+            paths.extend(walk(arg, excludes));
+        } else if !is_excluded(Path::new(arg), excludes) {
+            paths.push(arg.to_string());
+        }
+    }
+
+    // a file can be matched by more than one argument, only audit it once
+    let mut seen: HashSet<String> = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    Ok(paths)
+}
+
+fn is_excluded(path: &Path, excludes: &[ExcludePattern]) -> bool {
+    excludes.iter().any(|exclude| exclude.is_match(path))
+}
+
+fn is_glob(arg: &str) -> bool {
+    arg.contains(['*', '?', '[', '{'])
+}
+
+// the directory to start walking from: every path component before the first one that
+// contains a glob character
+fn glob_base(pattern: &str) -> String {
+    let base: Vec<&str> = pattern
+        .split('/')
+        .take_while(|component| !is_glob(component))
+        .collect();
+    match base.join("/").as_str() {
+        "" if pattern.starts_with('/') => "/".to_string(),
+        "" => ".".to_string(),
+        base => base.to_string(),
+    }
+}
+
+// find every file under `root` with an audited extension that isn't ignored or excluded
+fn walk(root: &str, excludes: &[ExcludePattern]) -> Vec<String> {
+    let mut paths: Vec<String> = vec![];
+    let excludes = excludes.to_vec();
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .filter_entry(move |entry| !is_excluded(entry.path(), &excludes))
+        .build();
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                eprintln!("Failed to read {}: {}", root, error);
+                continue;
+            }
+        };
+        let is_file = entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file());
+        let is_audited = entry
+            .path()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| AUDITED_EXTENSIONS.contains(&extension));
+        if is_file && is_audited {
+            paths.push(entry.path().to_string_lossy().to_string());
+        }
+    }
+
+    paths.sort();
+    paths
+}
+
+// exclude patterns are relative to the current directory, like a .gitignore at its root
+fn relative_path(path: &Path) -> &Path {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    match std::env::current_dir() {
+        Ok(current_dir) => path.strip_prefix(current_dir).unwrap_or(path),
+        Err(_) => path,
+    }
+}
+
+/// A `.gitignore`-style pattern of files to leave out of the audit. A pattern containing a slash
+/// is anchored to the start of the path, anything else matches a file or directory name at any
+/// depth.
+#[derive(Clone)]
+pub struct ExcludePattern {
+    globs: GlobSet,
+    /// Whether the pattern contains a slash, so it's anchored to a directory.
+    anchored: bool,
+    /// The directory an anchored pattern is relative to, when it isn't the current directory.
+    root: Option<PathBuf>,
+}
+
+impl ExcludePattern {
+    /// Parses a pattern, failing if it isn't a valid glob.
+    pub fn parse(pattern: &str) -> Result<ExcludePattern, String> {
+        let invalid =
+            |error: globset::Error| format!("invalid glob '{}': {}", pattern, error.kind());
+        let trimmed = pattern.trim_end_matches('/');
+        let anchored = match trimmed.strip_prefix('/') {
+            Some(trimmed) => trimmed.to_string(),
+            None if trimmed.contains('/') => trimmed.trim_start_matches("./").to_string(),
+            None => format!("**/{}", trimmed),
+        };
+        let is_anchored = trimmed.contains('/');
+        // match the directory itself as well as everything in it
+        let mut builder = GlobSetBuilder::new();
+        for glob in [anchored.clone(), format!("{}/**", anchored)] {
+            builder.add(
+                GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(invalid)?,
+            );
+        }
+        Ok(ExcludePattern {
+            globs: builder.build().map_err(invalid)?,
+            anchored: is_anchored,
+            root: None,
+        })
+    }
+
+    /// Anchors the pattern, if it contains a slash, to `directory` instead of the current
+    /// directory, like a `.gitignore` in that directory.
+    pub fn relative_to(mut self, directory: &Path) -> Self {
+        if self.anchored {
+            let directory = match directory.as_os_str().is_empty() {
+                true => Path::new("."),
+                false => directory,
+            };
+            self.root = Some(
+                directory
+                    .canonicalize()
+                    .unwrap_or_else(|_| directory.to_path_buf()),
+            );
+        }
+        self
+    }
+
+    // whether the pattern matches the file or directory
+    fn is_match(&self, path: &Path) -> bool {
+        match &self.root {
+            Some(root) => path
+                .canonicalize()
+                .ok()
+                .and_then(|path| Some(self.globs.is_match(path.strip_prefix(root).ok()?)))
+                .unwrap_or(false),
+            None => self.globs.is_match(relative_path(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{audited_rules, Audit};
+    use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+
+    #[test]
+    fn blanks_the_declaration_after_an_ignore_comment() {
+        let css = ":root {\n  /* css-audit-ignore */\n  --a: url(\"a;b\");\n  --b: blue;\n}";
+        let (blanked, unused) = blank_ignored_declarations(css);
+        assert_eq!(
+            blanked,
+            format!(
+                ":root {{\n  /* css-audit-ignore */\n{}\n  --b: blue;\n}}",
+                " ".repeat(18)
+            )
+        );
+        assert!(unused.is_empty());
+    }
+
+    #[test]
+    fn keeps_columns_when_blanking() {
+        let css = ".a { /* css-audit-ignore */ content: \"é😀\"; color: red; }";
+        let (blanked, _) = blank_ignored_declarations(css);
+        // lightningcss counts columns in UTF-16 code units
+        let column = |text: &str| text[..text.find("color").unwrap()].encode_utf16().count();
+        assert_eq!(column(&blanked), column(css));
+        assert!(blanked.ends_with("color: red; }"));
+    }
+
+    #[test]
+    fn ignores_comment_text_in_strings_and_urls() {
+        let css = ".a { content: \"/* css-audit-ignore */\"; color: red; }\n\
+                   .b { background: url(/*/css-audit-ignore*/x.png); color: red; }";
+        let (blanked, unused) = blank_ignored_declarations(css);
+        assert_eq!(blanked, css);
+        assert!(unused.is_empty());
+    }
+
+    #[test]
+    fn reports_ignore_comments_without_a_declaration() {
+        let css = "/* css-audit-ignore */\n.a { color: red; }\n.b {\n  color: red; /* css-audit-ignore */\n}";
+        let (blanked, unused) = blank_ignored_declarations(css);
+        assert_eq!(blanked, css);
+        assert_eq!(unused, vec![(0, 1), (3, 15)]);
+    }
+
+    #[test]
+    fn extracts_style_elements_with_their_offsets() {
+        let component = "<template><p /></template>\n<style>\n.a { color: red; }\n</style>\n  <STYLE module>.b {}</STYLE>";
+        let blocks = extract("App.vue", component);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].css, "\n.a { color: red; }\n");
+        assert_eq!((blocks[0].line_offset, blocks[0].column_offset), (1, 7));
+        assert!(!blocks[0].css_modules);
+        assert_eq!(
+            blocks[0].location("App.vue", 1, 1).to_string(),
+            "App.vue:3:1"
+        );
+        assert_eq!(blocks[1].css, ".b {}");
+        assert_eq!((blocks[1].line_offset, blocks[1].column_offset), (4, 16));
+        assert!(blocks[1].css_modules);
+        assert_eq!(
+            blocks[1].location("App.vue", 0, 1).to_string(),
+            "App.vue:5:17"
+        );
+    }
+
+    #[test]
+    fn skips_style_elements_in_other_languages() {
+        let component = "<style lang=\"scss\">$x: 1;</style>\n<style lang='postcss'>.a {}</style>";
+        let blocks = extract("App.svelte", component);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].css, ".a {}");
+    }
+
+    #[test]
+    fn extracts_tagged_templates_with_their_offsets() {
+        let source = "const a = 1;\nconst styles = css`\n  color: red;\n`;";
+        let blocks = extract("styles.ts", source);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].css, "&{\n  color: red;\n}");
+        assert_eq!((blocks[0].line_offset, blocks[0].column_offset), (1, 19));
+        assert_eq!(
+            blocks[0].location("styles.ts", 0, 3).to_string(),
+            "styles.ts:2:20"
+        );
+        assert_eq!(
+            blocks[0].location("styles.ts", 1, 3).to_string(),
+            "styles.ts:3:3"
+        );
+    }
+
+    #[test]
+    fn keeps_lines_of_interpolations_spanning_several() {
+        let source = "css`a: ${theme({\n  close: '}',\n})};\nb: 1;`";
+        let blocks = extract("styles.js", source);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(
+            blocks[0].css,
+            format!("&{{a: {}\n\n;\nb: 1;}}", INTERPOLATION_PLACEHOLDER)
+        );
+        assert_eq!(blocks[0].css.lines().count(), source.lines().count());
+    }
+
+    #[test]
+    fn keeps_escaped_backticks_in_tagged_templates() {
+        let source = "css`content: \"\\`\"; color: red;`; css`b: 1;`";
+        let blocks = extract("styles.js", source);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].css, "&{content: \"\\`\"; color: red;}");
+        assert_eq!(blocks[1].css, "&{b: 1;}");
+    }
+
+    #[test]
+    fn skips_other_tags_ending_in_css() {
+        let source = "const a = styled.css`color: red;`;\nconst b = mycss`color: blue;`;";
+        assert!(extract("styles.js", source).is_empty());
+    }
+
+    #[test]
+    fn audits_declarations_in_tagged_templates() {
+        let source = "const a = css`\n  color: var(--color-primary);\n`;\n\
+                      const b = css`.b { color: var(--color-secondary); }`;";
+        let blocks = extract("styles.js", source);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].css, ".b { color: var(--color-secondary); }");
+
+        let mut audit = Audit::default();
+        for block in &blocks {
+            let stylesheet = StyleSheet::parse(&block.css, ParserOptions::default()).unwrap();
+            for rule in audited_rules(&stylesheet.rules.0) {
+                let loc = rule.loc();
+                audit.record_rule(&rule, &block.location("styles.js", loc.line, loc.column));
+            }
+        }
+        let usages: Vec<(&str, &str, String)> = audit
+            .usages
+            .iter()
+            .map(|usage| {
+                let location = usage.location.to_string();
+                (usage.property.as_str(), usage.selector.as_str(), location)
+            })
+            .collect();
+        assert_eq!(
+            usages,
+            vec![
+                ("--color-primary", "&", "styles.js:1:15".to_string()),
+                ("--color-secondary", ".b", "styles.js:4:15".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unterminated_tagged_templates() {
+        let source = "const a = css`.a { color: red; }`;\nconst s = css`.b { color: var(--x); }";
+        let blocks = extract("styles.js", source);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].css, ".a { color: red; }");
+        assert!(extract("styles.js", "css`.a { color: ${theme.color").is_empty());
+        assert!(extract("styles.js", "css`").is_empty());
+    }
+}
@@ -1,555 +1,904 @@
-use lightningcss::declaration::DeclarationBlock;
+mod audit;
+mod baseline;
+mod breakdown;
+mod cli;
+mod config;
+mod css_modules;
+mod dead_selectors;
+mod delimited;
+mod diagnostics;
+mod diff;
+mod findings;
+mod graph;
+mod html;
+mod inputs;
+mod markdown;
+mod patterns;
+mod sarif;
+mod schema;
+mod summary;
+
+use audit::AuditedRule;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use cli::OutputFormats;
+use findings::Severity;
+use inputs::SourceLocation;
 use lightningcss::printer::{Printer, PrinterOptions};
-use lightningcss::properties::custom::{TokenList, TokenOrValue};
-use lightningcss::properties::Property;
-use lightningcss::rules::keyframes::KeyframesName;
-use lightningcss::rules::CssRule;
 use lightningcss::stylesheet::{ParserOptions, StyleSheet};
 use lightningcss::traits::ToCss;
 use serde::Serialize;
-use std::env;
-use xxhash_rust::xxh3::xxh3_64;
-
-enum OutputFormats {
-    Terminal,
-    JSON,
-    HTML,
-    None,
-}
-
-const HTML_TEMPLATE: &str = r#"
-<!doctype html>
-<html lang="en">
-  <head>
-    <meta charset="UTF-8" />
-    <title>CSS Custom Properties Audit</title>
-    <link
-      rel="stylesheet"
-      href="https://unpkg.com/@dryan-llc/mnml.css"
-      crossorigin="anonymous"
-    />
-    <meta name="viewport" content="width=device-width, initial-scale=1" />
-    <style>
-        html {
-            scroll-behavior: smooth;
-        }
+use std::collections::{BTreeMap, HashSet};
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, RwLock};
 
-        .audit-grid {
-            display: grid;
-            row-gap: 2rem;
-            column-gap: 2rem;
-
-            @container --mnml-container (width >= 48rem) {
-                grid-template-columns: 1fr 3fr;            
-            }
-        }
+// write a function that takes anything with a .to_css method and uses that to return a string
+pub fn to_css(thing: impl ToCss) -> String {
+    let mut dest = String::with_capacity(1);
+    let mut printer = Printer::new(&mut dest, PrinterOptions::default());
+    thing.to_css(&mut printer).unwrap();
+    dest
+}
 
-        .site-header {
-            grid-column: 1 / -1;
-            display: grid;
-            row-gap: 1rem;
+#[derive(Serialize)]
+struct CssRulesHashMap {
+    selector: String,
+    rules: Vec<String>,
+}
 
-            h1 {
-                margin-block: 0;
-            }
+// render each group as its name followed by an indented list of its entries
+fn render_groups(groups: &[(String, Vec<String>)]) -> String {
+    let mut text = String::new();
+    for (index, (key, value)) in groups.iter().enumerate() {
+        if index > 0 {
+            text.push('\n');
         }
-
-        h2, css-audit-minimap {
-            font-family: var(--mnml--font--monospace);
+        text.push_str(&format!("{}\n", key));
+        for entry in value {
+            text.push_str(&format!("  {}\n", entry));
         }
+    }
+    text
+}
 
-        h2 ~ h2 {
-            margin-block-start: 3rem;
-        }
+// render a value as pretty-printed JSON on its own line
+fn render_json(value: &impl Serialize) -> String {
+    format!("{}\n", serde_json::to_string_pretty(value).unwrap())
+}
 
-        h2 .count {
-            font-family: var(--mnml--font--sans);
-            font-size: 0.75em;
+// pair each --output with the --format given before it, leaving the rest to go to stdout
+fn outputs<F: Copy>(
+    formats: &[F],
+    outputs: &[PathBuf],
+    matches: &ArgMatches,
+) -> Vec<(F, Option<PathBuf>)> {
+    let mut command = cli::Cli::command();
+    // formats that weren't given on the command line, like the default, come before any --output
+    let format_indices: Vec<usize> = match matches.value_source("format") {
+        Some(ValueSource::CommandLine) => matches
+            .indices_of("format")
+            .map_or(vec![], |indices| indices.collect()),
+        _ => vec![0; formats.len()],
+    };
+    let output_indices: Vec<usize> = matches
+        .indices_of("output")
+        .map_or(vec![], |indices| indices.collect());
+
+    let mut paired: Vec<(F, Option<PathBuf>)> =
+        formats.iter().map(|format| (*format, None)).collect();
+    for (output, output_index) in outputs.iter().zip(output_indices) {
+        let Some(position) = format_indices
+            .iter()
+            .rposition(|&format_index| format_index < output_index)
+        else {
+            command
+                .error(
+                    ErrorKind::WrongNumberOfValues,
+                    "each --output needs a --format before it",
+                )
+                .exit();
+        };
+        if paired[position].1.is_some() {
+            command
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "each --format can only be written to one --output",
+                )
+                .exit();
         }
+        paired[position].1 = Some(output.clone());
+    }
+    if paired.iter().filter(|(_, output)| output.is_none()).count() > 1 {
+        command
+            .error(
+                ErrorKind::ArgumentConflict,
+                "only one --format can be written to stdout; give the others an --output",
+            )
+            .exit();
+    }
+    paired
+}
 
-        css-audit-minimap {
-            display: flex;
-            flex-direction: column;
-            row-gap: 1rem;
+// print an error that keeps the command from running, and exit with FAILURE_EXIT_CODE
+fn fail(error: impl std::fmt::Display) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(FAILURE_EXIT_CODE.into());
+}
 
-            a.dimmed {
-                pointer-events: none;
-                opacity: 0.5;
-            }
-        }
+// use a value read from the config or another file, exiting with an error if it's invalid
+fn configured<T>(value: Result<T, String>) -> T {
+    value.unwrap_or_else(|error| fail(error))
+}
 
-        css-audit-search {
-            display: flow-root;
+// write the output of one format to its file, or to stdout when it has none
+fn write_output(output: Option<&PathBuf>, contents: &str) {
+    match output {
+        Some(path) => std::fs::write(path, contents)
+            .unwrap_or_else(|error| fail(format!("Failed to write {}: {}", path.display(), error))),
+        None => print!("{}", contents),
+    }
+}
 
-            &:not(:defined) {
-                display: none;
-            }
+/// Everything found in the audited files.
+struct Audited {
+    audit: audit::Audit,
+    /// The path of every audited file, in the order they were read.
+    inputs: Vec<String>,
+    /// The exported classes of any CSS modules, with the custom properties each one uses.
+    css_modules: css_modules::CssModules,
+    /// The contents of every file, when they were kept for the HTML report.
+    sources: BTreeMap<String, String>,
+    /// The style rules that match nothing in the markup, if any was given.
+    dead_rules: Vec<dead_selectors::DeadRule>,
+    /// The custom properties still used by a rule that matches the markup.
+    live_custom_properties: HashSet<String>,
+    /// The files that couldn't be read or parsed, which were left out of the audit, and any
+    /// warnings from parsing the rest.
+    diagnostics: Vec<diagnostics::Diagnostic>,
+}
 
-            form {
-                display: grid;
-                grid-template-columns: 1fr auto;
-                column-gap: 1rem;
-            }
-        }
-    </style>
-  </head>
-  <body>
-    <div class="container">
-        <div class="audit-grid">
-            <header class="site-header">
-                <h1>CSS Custom Properties Audit</h1>
-
-                <css-audit-search>
-                    <form method="GET" action=".">
-                        <label for="search" class="reader-only">Search</label>
-                        <input type="search" id="search" name="search" />
-                        <button type="submit" data-color="primary">Search</button>
-                    </form>
-                </css-audit-search>
-            </header>
-
-            <css-audit-minimap role="navigation"></css-audit-minimap>
-
-            <main></main>
-
-            <script type="module">
-                class CssAuditMinimap extends HTMLElement {
-                    connectedCallback() {}
-                }
+// list the problems reading and parsing the inputs as a group, if there were any
+fn diagnostic_group(diagnostics: &[diagnostics::Diagnostic]) -> Option<(String, Vec<String>)> {
+    let entries: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| format!("{}: {}", diagnostic.severity.name(), diagnostic.label()))
+        .collect();
+    (!entries.is_empty()).then(|| ("Diagnostics".to_string(), entries))
+}
 
-                customElements.define("css-audit-minimap", CssAuditMinimap);
+/// The exit code when the lint command finds any problem at error severity.
+const LINT_ERROR_EXIT_CODE: u8 = 1;
+
+/// The exit code when the command can't run as asked, such as for an invalid config, missing
+/// inputs or a report that can't be written. It's the same code clap exits with for usage
+/// errors, so it never means that problems were found.
+const FAILURE_EXIT_CODE: u8 = 2;
+
+/// The exit code when any input couldn't be read or parsed, after auditing the rest.
+const INPUT_ERROR_EXIT_CODE: u8 = 3;
+
+// exit with INPUT_ERROR_EXIT_CODE if any input was left out of the audit, but not for warnings
+fn exit_code(diagnostics: &[diagnostics::Diagnostic]) -> ExitCode {
+    match diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        true => ExitCode::from(INPUT_ERROR_EXIT_CODE),
+        false => ExitCode::SUCCESS,
+    }
+}
 
-                class CssAuditSearch extends HTMLElement {
-                    connectedCallback() {
-                        this.form = this.querySelector("form");
-                        this.input = this.querySelector("input");
-                        this.button = this.querySelector("button");
+fn main() -> ExitCode {
+    let matches = cli::Cli::command().get_matches();
+    let cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    let config = configured(config::load(cli.config.as_deref()));
+    // the matches of the command that was run, for where its options were given
+    let matches = matches
+        .subcommand()
+        .map_or(&matches, |(_, matches)| matches);
+    match cli.command {
+        None => usages(cli.usages, &config, matches),
+        Some(cli::Command::Usages(args)) => usages(args, &config, matches),
+        Some(cli::Command::Definitions(args)) => definitions(args, &config, matches),
+        Some(cli::Command::Lint(args)) => lint(args, &config, matches),
+        Some(cli::Command::Diff(args)) => diff(args, &config, matches),
+        Some(cli::Command::Graph(args)) => graph(args, &config, matches),
+    }
+}
 
-                        this.form.addEventListener("submit", this.handleSubmit.bind(this));
-                    }
+// read, parse and audit every file given, checking each style rule against any markup
+fn audit_inputs(
+    input: &cli::InputArgs,
+    config: &config::Config,
+    documents: &[scraper::Html],
+    keep_sources: bool,
+) -> Audited {
+    // walk any directories and expand any glob patterns into the files they contain, falling
+    // back to the configured inputs when none are given
+    let paths = match input.inputs.is_empty() {
+        true => config.inputs(),
+        false => input.inputs.clone(),
+    };
+    let excludes = [input.exclude.clone(), configured(config.excludes())].concat();
+    let mut stylesheets =
+        inputs::expand_paths(&paths, &excludes).unwrap_or_else(|error| fail(error));
 
-                    handleSubmit(event) {
-                        event.preventDefault();
-                        const search = this.input.value;
-                        const headings = Array.from(document.querySelectorAll("h2"));
-                        const matchingHeadings = headings.filter(heading => heading.textContent.includes(search));
+    // paths that match no files are a mistake, rather than a reason to read from stdin
+    if stylesheets.is_empty() && !paths.is_empty() {
+        fail(format!("No stylesheets found in {}", paths.join(", ")));
+    }
 
-                        if (matchingHeadings.length === 0) {
-                            alert("No matches found");
-                            return;
-                        }
+    // with no stylesheets given but something piped in, read the stylesheet from stdin
+    let implicit_stdin = paths.is_empty() && !std::io::stdin().is_terminal();
+    if implicit_stdin {
+        stylesheets.push("-".to_string());
+    }
 
-                        const matchingMinimapLinks = matchingHeadings.map(heading => {
-                            const id = heading.getAttribute("id");
-                            return document.querySelector(`a[href='#${id}']`);
-                        });
+    // if there are no stylesheets, print an error message and exit
+    if stylesheets.is_empty() {
+        fail("No stylesheets provided");
+    }
 
-                        const nonMatchingMinimapLinks = Array.from(document.querySelectorAll("css-audit-minimap a")).filter(link => !matchingMinimapLinks.includes(link));
+    // collect every custom property used or defined in all stylesheets, along with the
+    // selectors that use or define it
+    let mut audit = audit::Audit {
+        options: audit::AuditOptions {
+            ignored_prefixes: config.ignored_prefixes(),
+            include: [
+                input.include_property.clone(),
+                configured(config.include_properties()),
+            ]
+            .concat(),
+            exclude: [
+                input.exclude_property.clone(),
+                configured(config.exclude_properties()),
+            ]
+            .concat(),
+            targets: configured(config.targets()),
+        },
+        ..audit::Audit::default()
+    };
+    let mut inputs: Vec<String> = vec![];
 
-                        matchingHeadings.forEach(heading => {
-                            heading.removeAttribute('hidden');
-                            heading.nextElementSibling.closest('ul')?.removeAttribute('hidden');
-                        });
+    // when checking against markup, keep track of the rules that match nothing and of the
+    // custom properties that are still used by a live rule
+    let mut dead_rules: Vec<dead_selectors::DeadRule> = vec![];
+    let mut live_custom_properties: HashSet<String> = HashSet::new();
 
-                        headings.filter(heading => !matchingHeadings.includes(heading)).forEach(heading => {
-                            heading.setAttribute('hidden', true);
-                            heading.nextElementSibling.closest('ul')?.setAttribute('hidden', true);
-                        });
+    let mut css_modules = css_modules::CssModules::default();
+    let mut sources: BTreeMap<String, String> = BTreeMap::new();
+    let mut diagnostics: Vec<diagnostics::Diagnostic> = vec![];
 
-                        matchingMinimapLinks.forEach(link => link.classList.remove('dimmed'));
-                        nonMatchingMinimapLinks.forEach(link => link.classList.add('dimmed'));
-                    }
+    for path in &stylesheets {
+        // get the contents of the stylesheet, reading from stdin when the path is -
+        let (path, contents) = if path == "-" {
+            let mut contents = String::new();
+            let read = std::io::stdin().read_to_string(&mut contents);
+            // stdin that isn't a terminal but has nothing in it, like /dev/null in cron or CI,
+            // means no stylesheets were given rather than an empty one
+            if implicit_stdin && read.is_ok() && contents.trim().is_empty() {
+                fail("No stylesheets provided");
+            }
+            (&input.stdin_filename, read.map(|_| contents))
+        } else {
+            (path, std::fs::read_to_string(path))
+        };
+        // report files that can't be read, then carry on with the rest
+        let contents = match contents {
+            Ok(contents) => contents,
+            Err(error) => {
+                let diagnostic =
+                    diagnostics::Diagnostic::file(Severity::Error, path, error.to_string());
+                eprintln!("{}", diagnostic);
+                diagnostics.push(diagnostic);
+                continue;
+            }
+        };
+        inputs.push(path.to_string());
+        if keep_sources {
+            sources.insert(path.to_string(), contents.clone());
+        }
+        for block in inputs::extract(path, &contents) {
+            for &(line, column) in &block.unused_ignore_comments {
+                let diagnostic = diagnostics::Diagnostic::at(
+                    Severity::Warning,
+                    block.location(path, line, column),
+                    &contents,
+                    "this css-audit-ignore comment has no effect, since no declaration follows it"
+                        .to_string(),
+                );
+                eprintln!("{}", diagnostic);
+                diagnostics.push(diagnostic);
+            }
+            // with error recovery, invalid rules and declarations are skipped and reported as
+            // warnings instead of failing the whole block
+            let warnings = Arc::new(RwLock::new(vec![]));
+            let options = ParserOptions {
+                filename: path.to_string(),
+                css_modules: block
+                    .css_modules
+                    .then(lightningcss::css_modules::Config::default),
+                error_recovery: input.error_recovery,
+                warnings: Some(warnings.clone()),
+                ..ParserOptions::default()
+            };
+            let parsed = StyleSheet::parse(&block.css, options);
+            for warning in warnings.read().unwrap().iter() {
+                let diagnostic = diagnostics::Diagnostic::parse(
+                    Severity::Warning,
+                    warning,
+                    &block,
+                    path,
+                    &contents,
+                );
+                eprintln!("{}", diagnostic);
+                diagnostics.push(diagnostic);
+            }
+            let stylesheet = match parsed {
+                Ok(stylesheet) => stylesheet,
+                Err(error) => {
+                    let diagnostic = diagnostics::Diagnostic::parse(
+                        Severity::Error,
+                        &error,
+                        &block,
+                        path,
+                        &contents,
+                    );
+                    eprintln!("{}", diagnostic);
+                    diagnostics.push(diagnostic);
+                    continue;
                 }
-
-                customElements.define("css-audit-search", CssAuditSearch);
-            </script>
-        </div>
-    </div>
-  </body>
-</html>
-"#;
-
-// write a function that takes anything with a .to_css method and uses that to return a string
-pub fn to_css(thing: impl ToCss) -> String {
-    let mut dest = String::with_capacity(1);
-    let mut printer = Printer::new(&mut dest, PrinterOptions::default());
-    thing.to_css(&mut printer).unwrap();
-    return dest;
-}
-
-#[derive(Serialize)]
-struct CssRulesHashMap {
-    selector: String,
-    rules: Vec<String>,
-}
-
-fn handle_declarations(
-    selectors: &Vec<String>,
-    declarations: &DeclarationBlock,
-) -> std::collections::HashMap<String, Vec<String>> {
-    let mut custom_properties: std::collections::HashMap<String, Vec<String>> =
-        std::collections::HashMap::new();
-
-    for declaration in &declarations.declarations {
-        match declaration {
-            Property::Unparsed(unparsed) => match &unparsed.value {
-                TokenList(tokens) => {
-                    for token in tokens {
-                        match token {
-                            TokenOrValue::Var(var) => {
-                                // convert ident to string
-                                let _ident = var.name.ident.to_string();
-                                let ident = _ident.as_str();
-                                // if ident starts with --__, skip it
-                                if ident.starts_with("--__") {
-                                    continue;
-                                }
-                                // if the ident isn't in custom_properties, add it as an empty array
-                                if !custom_properties.contains_key(ident) {
-                                    custom_properties.insert(ident.to_string(), vec![]);
-                                }
-                                for selector in selectors {
-                                    // convert selector.iter() to a string as a variable
-                                    custom_properties
-                                        .get_mut(ident)
-                                        .unwrap()
-                                        .push(selector.to_string());
-                                }
-                            }
-                            _ => {}
+            };
+            for rule in audit::audited_rules(&stylesheet.rules.0) {
+                let loc = rule.loc();
+                let location = block.location(path, loc.line, loc.column);
+                let custom_properties_in_rule = audit.record_rule(&rule, &location);
+
+                if !documents.is_empty() {
+                    let dead_rule = match &rule {
+                        AuditedRule::Style { context, rule } => rule
+                            .selectors
+                            .0
+                            .iter()
+                            .all(|selector| !dead_selectors::matches_any(selector, documents))
+                            .then(|| audit::label(context, &to_css(&rule.selectors))),
+                        AuditedRule::Keyframes { .. } => None,
+                    };
+                    match dead_rule {
+                        Some(selector) => {
+                            let mut properties = custom_properties_in_rule.clone();
+                            properties.sort();
+                            dead_rules.push(dead_selectors::DeadRule {
+                                selector,
+                                location: location.clone(),
+                                custom_properties: properties,
+                            });
                         }
+                        None => live_custom_properties.extend(custom_properties_in_rule.clone()),
                     }
                 }
-            },
-            _ => {}
+
+                if let (true, AuditedRule::Style { rule, .. }) = (block.css_modules, &rule) {
+                    css_modules.record_rule(path, rule, custom_properties_in_rule.iter());
+                }
+            }
         }
     }
 
-    return custom_properties;
-}
-
-fn main() {
-    // if --help is passed as an argument, print the help message and exit
-
-    if env::args().any(|x| x == "--help") {
-        println!("Parses one or more CSS stylesheets and outputs a list of custom properties and the selectors that use them.");
-        println!();
-        println!("Usage: css-audit [options] <stylesheet>...");
-        println!();
-        println!("Options:");
-        println!("  --help             Print this help message and exit");
-        println!("  --format=terminal  Output to the terminal (default)");
-        println!("  --format=html      Output an HTML document");
-        println!("  --format=json      Output a JSON document");
-        println!("  --format=none      Do not output anything (useful for testing)");
-        println!();
-        println!("Examples:");
-        println!("  css-audit --format=html styles.css");
-        println!("  css-audit --format=json styles.css");
-        println!("  css-audit styles.css");
-        std::process::exit(0);
+    Audited {
+        audit,
+        inputs,
+        css_modules,
+        sources,
+        dead_rules,
+        live_custom_properties,
+        diagnostics,
     }
+}
 
-    let mut stylesheets: Vec<String> = env::args().collect();
-    // remove the first argument, which is the program name
-    stylesheets.remove(0);
-
-    // get any --format argument and remove it from the stylesheets
-    let format: OutputFormats = match stylesheets.iter().position(|x| x.starts_with("--format")) {
-        Some(index) => {
-            // support both format html and format=html
-            let arg = stylesheets[index].clone();
-            if arg.contains("=") {
-                let format = arg.split("=").collect::<Vec<&str>>()[1];
-                stylesheets.remove(index);
-                match format {
-                    "json" => OutputFormats::JSON,
-                    "html" => OutputFormats::HTML,
-                    "none" => OutputFormats::None,
-                    _ => OutputFormats::Terminal,
+fn usages(args: cli::UsagesArgs, config: &config::Config, matches: &ArgMatches) -> ExitCode {
+    let cli::UsagesArgs {
+        input,
+        format: formats,
+        output,
+        markup,
+        by_selector,
+        by_file,
+        summary: summary_only,
+        legacy_json,
+    } = args;
+    // use the configured formats unless any are given, and the terminal without either
+    let outputs = match (formats.is_empty(), config.formats.is_empty()) {
+        (true, false) if output.is_empty() => configured(config.formats()),
+        (true, _) => outputs(&[OutputFormats::Terminal], &output, matches),
+        (false, _) => outputs(&formats, &output, matches),
+    };
+    // without every fixture, rules matching only the missing ones would be reported as dead
+    let documents = configured(dead_selectors::load_documents(&markup));
+    // the HTML report shows the source around each usage, so keep the contents of every file
+    let Audited {
+        audit,
+        inputs,
+        css_modules,
+        sources,
+        dead_rules,
+        live_custom_properties,
+        diagnostics,
+    } = audit_inputs(
+        &input,
+        config,
+        &documents,
+        outputs
+            .iter()
+            .any(|(format, _)| matches!(format, OutputFormats::Html)),
+    );
+
+    if !documents.is_empty() {
+        let mut orphaned_custom_properties: Vec<String> = dead_rules
+            .iter()
+            .flat_map(|rule| rule.custom_properties.iter())
+            .filter(|property| !live_custom_properties.contains(*property))
+            .cloned()
+            .collect();
+        orphaned_custom_properties.sort();
+        orphaned_custom_properties.dedup();
+        let report = dead_selectors::DeadSelectorReport {
+            rules: dead_rules,
+            orphaned_custom_properties,
+        };
+
+        let mut groups: Vec<(String, Vec<String>)> = report
+            .rules
+            .iter()
+            .map(|rule| {
+                (
+                    format!("{}  ({})", rule.selector, rule.location),
+                    rule.custom_properties.clone(),
+                )
+            })
+            .collect();
+        groups.push((
+            "Custom properties that would become unused".to_string(),
+            report.orphaned_custom_properties.clone(),
+        ));
+        let mut html_groups = groups.clone();
+        html_groups.extend(diagnostic_group(&diagnostics));
+
+        for (format, output) in outputs {
+            let contents = match format {
+                OutputFormats::Terminal => render_groups(&groups),
+                OutputFormats::Json => render_json(&report),
+                OutputFormats::Html => {
+                    let audit = audit::Audit::default();
+                    let html = html::render_report(&html::Report {
+                        usage_groups: &[],
+                        other_groups: &html_groups,
+                        audit: &audit,
+                        breakdown: &breakdown::Breakdown::new(&audit, &[]),
+                        sources: &sources,
+                        view: "properties",
+                        summary: None,
+                    });
+                    format!("{}\n", html)
                 }
-            } else {
-                let format = stylesheets[index + 1].clone();
-                stylesheets.remove(index + 1);
-                stylesheets.remove(index);
-                match format.as_str() {
-                    "json" => OutputFormats::JSON,
-                    "html" => OutputFormats::HTML,
-                    "none" => OutputFormats::None,
-                    _ => OutputFormats::Terminal,
+                OutputFormats::Sarif => render_json(&sarif::render(
+                    &report.findings(&findings::Config::default()),
+                    &diagnostics,
+                    None,
+                )),
+                OutputFormats::Csv => delimited::dead_rules(&report, b','),
+                OutputFormats::Tsv => delimited::dead_rules(&report, b'\t'),
+                OutputFormats::Markdown => {
+                    markdown::dead_rules(&report) + &markdown::diagnostics(&diagnostics)
                 }
-            }
+                OutputFormats::None => continue,
+            };
+            write_output(output.as_ref(), &contents);
         }
-        None => OutputFormats::Terminal,
-    };
-
-    // remove any arguments that start with --
-    stylesheets.retain(|x| !x.starts_with("--"));
-
-    // if there are no stylesheets, print an error message and exit
-    if stylesheets.len() == 0 {
-        eprintln!("No stylesheets provided");
-        std::process::exit(1);
+        return exit_code(&diagnostics);
     }
 
-    // create a map of every custom property used in all stylesheets that contains
-    // an array of selectors that use that property
-    let mut custom_properties: std::collections::HashMap<String, Vec<String>> =
-        std::collections::HashMap::new();
+    // sort the custom properties by key, and group the usages of each by selector so every
+    // selector is listed once along with the places it was found
+    let mut custom_properties: BTreeMap<&str, BTreeMap<String, Vec<&SourceLocation>>> =
+        BTreeMap::new();
+    for usage in &audit.usages {
+        custom_properties
+            .entry(&usage.property)
+            .or_default()
+            .entry(audit::label(&usage.context, &usage.selector))
+            .or_default()
+            .push(&usage.location);
+    }
+    for selectors in custom_properties.values_mut() {
+        for locations in selectors.values_mut() {
+            locations.sort();
+            locations.dedup();
+        }
+    }
 
-    for path in &stylesheets {
-        // get the contents of the stylesheet
-        let contents = std::fs::read_to_string(path).expect("Failed to read stylesheet");
-        let mut stylesheet = StyleSheet::parse(&contents, ParserOptions::default())
-            .expect("Failed to parse stylesheet");
-        // loop over every CSSRule in stylesheet.rules
-        for mut rule in stylesheet.rules.0.drain(..) {
-            match &mut rule {
-                CssRule::Keyframes(rule) => {
-                    // println!("Keyframes: {:?}", keyframes);
-                    let name = match &rule.name {
-                        KeyframesName::Ident(ident) => format!("@keyframes {}", ident.to_string()),
-                        KeyframesName::Custom(string) => {
-                            format!("@keyframes {}", string.to_string())
-                        }
-                    };
-                    let selectors = vec![name.to_string()];
-                    for keyframe in rule.keyframes.iter() {
-                        let custom_properties_in_keyframe =
-                            handle_declarations(&selectors, &keyframe.declarations);
-                        for (key, value) in custom_properties_in_keyframe {
-                            if !custom_properties.contains_key(&key) {
-                                custom_properties.insert(key, value);
-                            } else {
-                                custom_properties.get_mut(&key).unwrap().extend(value);
-                            }
-                        }
-                    }
-                }
-                CssRule::CustomMedia(media) => {
-                    eprintln!("@custom-media is not supported: {:?}", media);
-                }
-                CssRule::Media(media) => {
-                    let mq = format!("@media {}", to_css(&media.query));
-                    for rule in &media.rules.0 {
-                        match rule {
-                            CssRule::Style(style) => {
-                                let selectors = style.selectors.0.to_vec();
-                                let selectors_as_strings: Vec<String> = selectors
-                                    .iter()
-                                    .map(|selector| format!("{}\n    {:?}", mq, selector.iter()))
-                                    .collect();
-                                let custom_properties_in_style =
-                                    handle_declarations(&selectors_as_strings, &style.declarations);
-                                for (key, value) in custom_properties_in_style {
-                                    if !custom_properties.contains_key(&key) {
-                                        custom_properties.insert(key, value);
-                                    } else {
-                                        custom_properties.get_mut(&key).unwrap().extend(value);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                CssRule::Supports(supports) => {
-                    // println!("Supports: {:?}", supports);
-                    let at_supports = format!("@supports {}", to_css(&supports.condition));
-                    for rule in &supports.rules.0 {
-                        match rule {
-                            CssRule::Style(style) => {
-                                let selectors = style.selectors.0.to_vec();
-                                let selectors_as_strings: Vec<String> = selectors
-                                    .iter()
-                                    .map(|selector| {
-                                        format!("{}\n    {:?}", at_supports, selector.iter())
-                                    })
-                                    .collect();
-                                let custom_properties_in_style =
-                                    handle_declarations(&selectors_as_strings, &style.declarations);
-                                for (key, value) in custom_properties_in_style.into_iter() {
-                                    if !custom_properties.contains_key(&key) {
-                                        custom_properties.insert(key, value);
-                                    } else {
-                                        custom_properties.get_mut(&key).unwrap().extend(value);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                CssRule::Container(container) => {
-                    // println!("Container: {:?}", container);
-                    let at_container = format!("@container {}", to_css(&container.condition));
-                    for rule in &container.rules.0 {
-                        match rule {
-                            CssRule::Style(style) => {
-                                let selectors = style.selectors.0.to_vec();
-                                let selectors_as_strings: Vec<String> = selectors
-                                    .iter()
-                                    .map(|selector| {
-                                        format!("{}\n    {:?}", at_container, selector.iter())
-                                    })
-                                    .collect();
-                                let custom_properties_in_style =
-                                    handle_declarations(&selectors_as_strings, &style.declarations);
-                                for (key, value) in custom_properties_in_style.into_iter() {
-                                    if !custom_properties.contains_key(&key) {
-                                        custom_properties.insert(key, value);
-                                    } else {
-                                        custom_properties.get_mut(&key).unwrap().extend(value);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                CssRule::LayerBlock(layer_block) => {
-                    let at_layer: String = if Option::is_some(&layer_block.name) {
-                        match &layer_block.name {
-                            Some(name) => format!("@layer {}", to_css(name)),
-                            None => "@layer".to_string(),
-                        }
-                    } else {
-                        "@layer".to_string()
-                    };
-                    for rule in &layer_block.rules.0 {
-                        match rule {
-                            CssRule::Style(style) => {
-                                let selectors = style.selectors.0.to_vec();
-                                let selectors_as_strings: Vec<String> = selectors
-                                    .iter()
-                                    .map(|selector| {
-                                        format!("{}\n    {:?}", at_layer, selector.iter())
-                                    })
-                                    .collect();
-                                let custom_properties_in_style =
-                                    handle_declarations(&selectors_as_strings, &style.declarations);
-                                for (key, value) in custom_properties_in_style.into_iter() {
-                                    if !custom_properties.contains_key(&key) {
-                                        custom_properties.insert(key, value);
-                                    } else {
-                                        custom_properties.get_mut(&key).unwrap().extend(value);
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                CssRule::Style(style) => {
-                    let selectors = style.selectors.0.to_vec();
-                    let selectors_as_strings: Vec<String> = selectors
+    let property_groups: Vec<(String, Vec<String>)> = custom_properties
+        .iter()
+        .map(|(key, selectors)| {
+            let entries = selectors
+                .iter()
+                .map(|(selector, locations)| {
+                    let locations: Vec<String> = locations
                         .iter()
-                        .map(|selector| format!("{:?}", selector.iter()))
+                        .map(|location| location.to_string())
                         .collect();
-                    let custom_properties_in_style =
-                        handle_declarations(&selectors_as_strings, &style.declarations);
-                    for (key, value) in custom_properties_in_style {
-                        if !custom_properties.contains_key(&key) {
-                            custom_properties.insert(key, value);
-                        } else {
-                            custom_properties.get_mut(&key).unwrap().extend(value);
-                        }
-                    }
-                }
-                CssRule::Scope(scope) => {
-                    // println!("Scope: {:?}", scope);
-                    eprintln!("@scope is not supported: {:?}", scope);
-                }
-                CssRule::Nesting(nesting) => {
-                    // println!("Nesting: {:?}", nesting);
-                    eprintln!("nesting is not supported: {:?}", nesting);
-                }
-                CssRule::StartingStyle(starting_style) => {
-                    // println!("StartingStyle: {:?}", starting_style);
-                    eprintln!("@starting-style is not supported: {:?}", starting_style);
-                }
-                CssRule::Property(property) => {
-                    // println!("Property: {:?}", property);
-                    eprintln!("@property is not supported: {:?}", property);
+                    format!("{}  ({})", selector, locations.join(", "))
+                })
+                .collect();
+            (key.to_string(), entries)
+        })
+        .collect();
+
+    // list each selector, with the places it was found, and the custom properties it reads
+    // and defines
+    let selector_groups: Vec<(String, Vec<String>)> = audit
+        .selectors()
+        .iter()
+        .map(|selector| {
+            let locations: Vec<String> = selector
+                .locations
+                .iter()
+                .map(|location| location.to_string())
+                .collect();
+            let entries = selector
+                .reads
+                .iter()
+                .map(|property| format!("reads {}", property))
+                .chain(
+                    selector
+                        .defines
+                        .iter()
+                        .map(|property| format!("defines {}", property)),
+                )
+                .collect();
+            (
+                format!(
+                    "{}  ({})",
+                    audit::label(&selector.context, &selector.selector),
+                    locations.join(", ")
+                ),
+                entries,
+            )
+        })
+        .collect();
+
+    // list each directory and then each file, with how many times it reads and defines each
+    // custom property
+    let breakdown = breakdown::Breakdown::new(&audit, &inputs);
+    let file_groups: Vec<(String, Vec<String>)> = breakdown
+        .directories
+        .iter()
+        .map(|directory| {
+            (
+                format!("{}/  ({})", directory.path, directory.summary()),
+                directory.property_counts(),
+            )
+        })
+        .chain(breakdown.files.iter().map(|file| {
+            (
+                format!("{}  ({})", file.path, file.summary()),
+                file.property_counts(),
+            )
+        }))
+        .collect();
+
+    let usage_groups = if by_selector {
+        selector_groups
+    } else if by_file {
+        file_groups
+    } else {
+        property_groups
+    };
+    let mut groups = usage_groups.clone();
+    // list the exported classes of any CSS modules after the custom properties
+    let module_groups = css_modules.groups();
+    groups.extend(module_groups.iter().cloned());
+    // the terminal output has the diagnostics on stderr already, but the HTML report lists them
+    // after everything else
+    let mut html_groups = module_groups.clone();
+    html_groups.extend(diagnostic_group(&diagnostics));
+
+    let summary = summary::Summary::new(&audit);
+    // finish the terminal output with the summary, where it's visible without scrolling back
+    groups.push(("Summary".to_string(), summary.lines()));
+
+    for (format, output) in outputs {
+        // HTML and SARIF output always include the summary alongside everything else
+        let contents = match format {
+            OutputFormats::Terminal if summary_only => {
+                render_groups(&[("Summary".to_string(), summary.lines())])
+            }
+            OutputFormats::Json if summary_only => render_json(&summary),
+            OutputFormats::Csv if summary_only => delimited::summary(&summary, b','),
+            OutputFormats::Tsv if summary_only => delimited::summary(&summary, b'\t'),
+            OutputFormats::Markdown if summary_only => markdown::summary(&summary),
+            OutputFormats::Terminal => render_groups(&groups),
+            OutputFormats::Json if legacy_json => {
+                // output JSON as a list of [{selector: string, rules: [string]}]
+                let mut json: Vec<CssRulesHashMap> = vec![];
+                for (key, value) in &custom_properties {
+                    let map = CssRulesHashMap {
+                        selector: key.to_string(),
+                        rules: value.keys().cloned().collect(),
+                    };
+                    json.push(map);
                 }
-                _ => {}
+                render_json(&json)
             }
-        }
+            OutputFormats::Json => render_json(&schema::Report::new(
+                &audit,
+                &css_modules,
+                &inputs,
+                &diagnostics,
+            )),
+            OutputFormats::Html => {
+                let view = if by_selector {
+                    "selectors"
+                } else if by_file {
+                    "files"
+                } else {
+                    "properties"
+                };
+                let html = html::render_report(&html::Report {
+                    usage_groups: &usage_groups,
+                    other_groups: &html_groups,
+                    audit: &audit,
+                    breakdown: &breakdown,
+                    sources: &sources,
+                    view,
+                    summary: Some(&summary),
+                });
+                format!("{}\n", html)
+            }
+            OutputFormats::Sarif => render_json(&sarif::render(
+                &findings::find(&audit, &findings::Config::default()),
+                &diagnostics,
+                Some(&summary),
+            )),
+            OutputFormats::Csv if by_file => delimited::files(&breakdown, b','),
+            OutputFormats::Tsv if by_file => delimited::files(&breakdown, b'\t'),
+            OutputFormats::Markdown if by_file => {
+                markdown::files(&breakdown)
+                    + &markdown::summary(&summary)
+                    + &markdown::diagnostics(&diagnostics)
+            }
+            OutputFormats::Csv if by_selector => delimited::selectors(&audit, b','),
+            OutputFormats::Tsv if by_selector => delimited::selectors(&audit, b'\t'),
+            OutputFormats::Markdown if by_selector => {
+                markdown::selectors(&audit, &css_modules)
+                    + &markdown::summary(&summary)
+                    + &markdown::diagnostics(&diagnostics)
+            }
+            OutputFormats::Csv => delimited::usages(&audit, b','),
+            OutputFormats::Tsv => delimited::usages(&audit, b'\t'),
+            OutputFormats::Markdown => {
+                markdown::usages(&audit, &css_modules)
+                    + &markdown::summary(&summary)
+                    + &markdown::diagnostics(&diagnostics)
+            }
+            OutputFormats::None => continue,
+        };
+        write_output(output.as_ref(), &contents);
     }
+    exit_code(&diagnostics)
+}
 
-    // sort and dedup the selectors in each custom property
-    for (_, selectors) in &mut custom_properties {
-        selectors.sort();
-        selectors.dedup();
+fn definitions(
+    args: cli::DefinitionsArgs,
+    config: &config::Config,
+    matches: &ArgMatches,
+) -> ExitCode {
+    let outputs = outputs(&args.format, &args.output, matches);
+    let Audited {
+        audit, diagnostics, ..
+    } = audit_inputs(&args.input, config, &[], false);
+
+    // list each custom property with the selectors that define it and their values
+    let mut definitions: BTreeMap<&str, Vec<&audit::Definition>> = BTreeMap::new();
+    for definition in &audit.definitions {
+        definitions
+            .entry(&definition.property)
+            .or_default()
+            .push(definition);
     }
 
-    // sort the custom properties by key
-    let mut custom_properties: Vec<(&String, &Vec<String>)> = custom_properties.iter().collect();
-    custom_properties.sort_by(|a, b| a.0.cmp(b.0));
+    for (format, output) in outputs {
+        let contents = match format {
+            cli::DefinitionFormats::Terminal => {
+                let groups: Vec<(String, Vec<String>)> = definitions
+                    .iter()
+                    .map(|(property, definitions)| {
+                        let entries = definitions
+                            .iter()
+                            .map(|definition| {
+                                format!(
+                                    "{}: {}  ({})",
+                                    audit::label(&definition.context, &definition.selector),
+                                    definition.value,
+                                    definition.location
+                                )
+                            })
+                            .collect();
+                        (property.to_string(), entries)
+                    })
+                    .collect();
+                render_groups(&groups)
+            }
+            cli::DefinitionFormats::Json => render_json(&definitions),
+            cli::DefinitionFormats::Csv => delimited::definitions(&audit, b','),
+            cli::DefinitionFormats::Tsv => delimited::definitions(&audit, b'\t'),
+            cli::DefinitionFormats::None => continue,
+        };
+        write_output(output.as_ref(), &contents);
+    }
+    exit_code(&diagnostics)
+}
 
-    match format {
-        OutputFormats::Terminal => {
-            let mut loop_count = 0;
-            for (key, value) in custom_properties {
-                if loop_count > 0 {
-                    println!();
-                }
-                println!("{}", key);
-                for selector in value {
-                    println!("  {}", selector);
-                }
-                loop_count += 1;
+fn lint(args: cli::LintArgs, config: &config::Config, matches: &ArgMatches) -> ExitCode {
+    let outputs = outputs(&args.format, &args.output, matches);
+    let baseline_path = args.baseline.clone().or_else(|| config.baseline());
+    if args.update_baseline && baseline_path.is_none() {
+        cli::Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--update-baseline needs a --baseline file to write to",
+            )
+            .exit();
+    }
+    let Audited {
+        audit, diagnostics, ..
+    } = audit_inputs(&args.input, config, &[], false);
+    // rules given on the command line take precedence over the configured ones
+    let mut severities = configured(config.rules());
+    severities.extend(args.rule);
+    let rules = findings::Config {
+        severities,
+        forbidden_prefixes: args
+            .forbidden_prefix
+            .into_iter()
+            .chain(config.forbidden_prefixes.iter().cloned())
+            .collect(),
+        naming: args
+            .naming_pattern
+            .or(config.naming_pattern.clone())
+            .map(|pattern| {
+                let mut values = config.naming_values.clone();
+                values.extend(args.naming_values);
+                configured(patterns::NamingPattern::new(&pattern, values))
+            }),
+    };
+    let mut findings = findings::find(&audit, &rules);
+
+    // leave out the findings in the baseline, first recording them all when updating it
+    if let Some(path) = &baseline_path {
+        let baseline = match args.update_baseline {
+            true => {
+                let baseline = baseline::Baseline::new(&findings, path);
+                configured(baseline.save(path));
+                eprintln!("Recorded {} findings in {}", findings.len(), path.display());
+                baseline
             }
+            false => configured(baseline::Baseline::load(path)),
+        };
+        let (new, known) = baseline.filter(findings);
+        if known > 0 && !args.update_baseline {
+            eprintln!("Left out {} findings recorded in {}", known, path.display());
         }
-        OutputFormats::JSON => {
-            // output JSON as a list of [{selector: string, rules: [string]}]
-            let mut json: Vec<CssRulesHashMap> = vec![];
-            for (key, value) in custom_properties {
-                let map = CssRulesHashMap {
-                    selector: key.to_string(),
-                    rules: value.clone(),
-                };
-                json.push(map);
-            }
-            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        findings = new;
+    }
+
+    for (format, output) in outputs {
+        let contents = match format {
+            cli::LintFormats::Terminal => findings
+                .iter()
+                .map(|finding| {
+                    format!(
+                        "{}: {} [{}]  ({})\n",
+                        finding.severity.name(),
+                        finding.message,
+                        finding.rule.id,
+                        finding.location
+                    )
+                })
+                .collect(),
+            cli::LintFormats::Json => render_json(&findings),
+            cli::LintFormats::Sarif => render_json(&sarif::render(&findings, &diagnostics, None)),
+            cli::LintFormats::None => continue,
+        };
+        write_output(output.as_ref(), &contents);
+    }
+
+    // inputs that couldn't be audited take priority, since the findings are incomplete
+    let has_errors = findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+    match exit_code(&diagnostics) {
+        ExitCode::SUCCESS if has_errors => ExitCode::from(LINT_ERROR_EXIT_CODE),
+        exit_code => exit_code,
+    }
+}
+
+fn diff(args: cli::DiffArgs, config: &config::Config, matches: &ArgMatches) -> ExitCode {
+    let outputs = outputs(&args.format, &args.output, matches);
+    // read a JSON report, or audit stylesheets into one, exiting with an error if it can't be
+    // read or isn't a report
+    let mut input_diagnostics: Vec<diagnostics::Diagnostic> = vec![];
+    let mut read_report = |path: &str| -> schema::Report<'static> {
+        if !path.ends_with(".json") {
+            let input = cli::InputArgs {
+                inputs: vec![path.to_string()],
+                exclude: vec![],
+                include_property: vec![],
+                exclude_property: vec![],
+                stdin_filename: "<stdin>".to_string(),
+                error_recovery: false,
+            };
+            let Audited {
+                audit,
+                inputs,
+                css_modules,
+                diagnostics,
+                ..
+            } = audit_inputs(&input, config, &[], false);
+            let report =
+                schema::Report::new(&audit, &css_modules, &inputs, &diagnostics).into_owned();
+            input_diagnostics.extend(diagnostics);
+            return report;
         }
-        OutputFormats::HTML => {
-            // replace the contents of HTML_TEMPLATE's <main> with h2 and ul elements
-            // then replace the contents of css-audit-minimap with links to the h2 elements
-            // use a hashed version of the selector as the id for the h2 elements
-
-            let template = HTML_TEMPLATE.to_string();
-            let mut sections: Vec<String> = vec![];
-            let mut minimap: Vec<String> = vec![];
-            for (key, value) in custom_properties {
-                let id: String = format!("selector-{:x}", xxh3_64(key.as_bytes()));
-                let h2 = format!(
-                    "<h2 id=\"{}\">{} <span class='count'>({})</span></h2>",
-                    id,
-                    key,
-                    value.len()
-                );
-                let ul = format!(
-                    "<ul>{}</ul>",
-                    value
-                        .into_iter()
-                        .map(|v| format!("<li>{}</li>", v.replace("\n", "\n<br>")))
-                        .collect::<Vec<String>>()
-                        .join("")
-                );
-                sections.push(format!("{}{}", h2, ul));
-                minimap.push(format!("<a href=\"#{}\">{}</a>", id, key));
-            }
-            let sections = sections.join("");
-            let minimap = minimap.join("");
-            let html = template
-                .replace("</main>", format!("{}</main>", &sections)[..].as_ref())
-                .replace(
-                    "</css-audit-minimap>",
-                    format!("{}</css-audit-minimap>", &minimap)[..].as_ref(),
-                );
-            println!("{}", html);
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|error| fail(format!("Failed to read {}: {}", path, error)));
+        let report: serde_json::Value = serde_json::from_str(&contents)
+            .unwrap_or_else(|error| fail(format!("Failed to parse {}: {}", path, error)));
+        if report["schema_version"] != schema::SCHEMA_VERSION {
+            fail(format!(
+                "{} is not a schema_version {} report from --format=json",
+                path,
+                schema::SCHEMA_VERSION
+            ));
         }
-        OutputFormats::None => {}
+        // a report missing anything it needs fails here, rather than looking empty
+        serde_json::from_value(report)
+            .unwrap_or_else(|error| fail(format!("Invalid report {}: {}", path, error)))
+    };
+    let deprecated = [args.deprecated, configured(config.deprecated_properties())].concat();
+    let diff = diff::Diff::new(
+        &read_report(&args.old),
+        &read_report(&args.new),
+        &deprecated,
+    );
+
+    for (format, output) in outputs {
+        let contents = match format {
+            cli::DiffFormats::Terminal => render_groups(&diff.groups()),
+            cli::DiffFormats::Json => render_json(&diff),
+        };
+        write_output(output.as_ref(), &contents);
+    }
+    exit_code(&input_diagnostics)
+}
+
+fn graph(args: cli::GraphArgs, config: &config::Config, matches: &ArgMatches) -> ExitCode {
+    let outputs = outputs(&args.format, &args.output, matches);
+    let Audited {
+        audit, diagnostics, ..
+    } = audit_inputs(&args.input, config, &[], false);
+    let graph = graph::Graph::new(&audit);
+
+    for (format, output) in outputs {
+        let contents = match format {
+            cli::GraphFormats::Dot => graph.dot(),
+            cli::GraphFormats::Mermaid => graph.mermaid(),
+            cli::GraphFormats::Json => render_json(&graph),
+        };
+        write_output(output.as_ref(), &contents);
     }
+    exit_code(&diagnostics)
 }
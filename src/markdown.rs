@@ -0,0 +1,341 @@
+use crate::audit::{Audit, Usage};
+use crate::breakdown::{Breakdown, Footprint};
+use crate::css_modules::CssModules;
+use crate::dead_selectors::DeadSelectorReport;
+use crate::diagnostics::Diagnostic;
+use crate::summary::{list, PropertyCount, Summary};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Renders the custom properties as Markdown: a summary table of every property, then a
+/// heading and table of the selectors using each one.
+pub fn usages(audit: &Audit, css_modules: &CssModules) -> String {
+    let mut grouped: BTreeMap<&str, Vec<&Usage>> = BTreeMap::new();
+    for usage in &audit.usages {
+        grouped.entry(&usage.property).or_default().push(usage);
+    }
+
+    let mut markdown = String::from("# CSS Custom Properties Audit\n\n");
+    markdown.push_str("| Property | Usages | Selectors |\n| --- | ---: | ---: |\n");
+    let mut sections = String::new();
+    for (property, usages) in &grouped {
+        // list each selector, context and location once
+        let mut rows: Vec<(String, String, String)> = usages
+            .iter()
+            .map(|usage| {
+                (
+                    usage.selector.clone(),
+                    usage.context.join(" "),
+                    usage.location.to_string(),
+                )
+            })
+            .collect();
+        rows.sort();
+        rows.dedup();
+        let mut selectors: Vec<&String> = rows.iter().map(|row| &row.0).collect();
+        selectors.sort();
+        selectors.dedup();
+
+        writeln!(
+            markdown,
+            "| [{}](#{}) | {} | {} |",
+            code(property),
+            anchor(&format!("{} ({})", property, rows.len())),
+            rows.len(),
+            selectors.len()
+        )
+        .unwrap();
+
+        writeln!(sections, "## {} ({})\n", code(property), rows.len()).unwrap();
+        sections.push_str("| Selector | Context | Location |\n| --- | --- | --- |\n");
+        for (selector, context, location) in &rows {
+            writeln!(
+                sections,
+                "| {} | {} | {} |",
+                code(selector),
+                if context.is_empty() {
+                    String::new()
+                } else {
+                    code(context)
+                },
+                cell(location)
+            )
+            .unwrap();
+        }
+        sections.push('\n');
+    }
+    markdown.push('\n');
+    markdown.push_str(&sections);
+
+    markdown.push_str(&css_modules_section(css_modules));
+    markdown
+}
+
+/// Renders the custom properties each selector reads and defines as a Markdown table.
+pub fn selectors(audit: &Audit, css_modules: &CssModules) -> String {
+    let mut markdown = String::from("# CSS Custom Properties by Selector\n\n");
+    markdown.push_str(
+        "| Selector | Context | Reads | Defines | Locations |\n| --- | --- | --- | --- | --- |\n",
+    );
+    for selector in audit.selectors() {
+        let reads: Vec<String> = selector.reads.iter().map(|p| code(p)).collect();
+        let defines: Vec<String> = selector.defines.iter().map(|p| code(p)).collect();
+        let locations: Vec<String> = selector
+            .locations
+            .iter()
+            .map(|location| cell(&location.to_string()))
+            .collect();
+        let context = selector.context.join(" ");
+        writeln!(
+            markdown,
+            "| {} | {} | {} | {} | {} |",
+            code(&selector.selector),
+            if context.is_empty() {
+                String::new()
+            } else {
+                code(&context)
+            },
+            reads.join(", "),
+            defines.join(", "),
+            locations.join("<br>")
+        )
+        .unwrap();
+    }
+    markdown.push('\n');
+    markdown.push_str(&css_modules_section(css_modules));
+    markdown
+}
+
+/// Renders the footprint of each directory and file as Markdown tables, followed by the custom
+/// properties each file reads and defines.
+pub fn files(breakdown: &Breakdown) -> String {
+    let mut markdown = String::from("# CSS Custom Properties by File\n\n");
+    if !breakdown.directories.is_empty() {
+        markdown.push_str("## Directories\n\n");
+        markdown.push_str(&footprint_table("Directory", &breakdown.directories));
+    }
+    markdown.push_str("## Files\n\n");
+    markdown.push_str(&footprint_table("File", &breakdown.files));
+
+    for file in &breakdown.files {
+        let properties = file.properties();
+        if properties.is_empty() {
+            continue;
+        }
+        writeln!(markdown, "### {}\n", code(&file.path)).unwrap();
+        markdown.push_str("| Property | Reads | Definitions |\n| --- | ---: | ---: |\n");
+        for (property, (reads, definitions)) in properties {
+            writeln!(
+                markdown,
+                "| {} | {} | {} |",
+                code(property),
+                reads,
+                definitions
+            )
+            .unwrap();
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+// a table of the counts in each footprint, with the number of files for directories
+fn footprint_table(heading: &str, footprints: &[Footprint]) -> String {
+    let with_files = footprints.iter().any(|footprint| footprint.is_directory);
+    let mut markdown = String::new();
+    writeln!(
+        markdown,
+        "| {} |{} Usages | Definitions | Properties read | Properties defined |",
+        heading,
+        if with_files { " Files |" } else { "" }
+    )
+    .unwrap();
+    writeln!(
+        markdown,
+        "| --- |{} ---: | ---: | ---: | ---: |",
+        if with_files { " ---: |" } else { "" }
+    )
+    .unwrap();
+    for footprint in footprints {
+        let files = match with_files {
+            true => format!(" {} |", footprint.files),
+            false => String::new(),
+        };
+        writeln!(
+            markdown,
+            "| {} |{} {} | {} | {} | {} |",
+            code(&footprint.path),
+            files,
+            footprint.usages,
+            footprint.definitions,
+            footprint.reads.len(),
+            footprint.defines.len()
+        )
+        .unwrap();
+    }
+    markdown.push('\n');
+    markdown
+}
+
+// a table of each CSS module's exported classes, if there are any
+fn css_modules_section(css_modules: &CssModules) -> String {
+    let mut markdown = String::new();
+    if !css_modules.modules.is_empty() {
+        markdown.push_str("## CSS modules\n\n");
+        markdown.push_str(
+            "| Module | Class | Composes | Custom properties |\n| --- | --- | --- | --- |\n",
+        );
+        for (path, classes) in &css_modules.modules {
+            for (name, class) in classes {
+                let composes: Vec<String> = class.composes.iter().map(|c| code(c)).collect();
+                let properties: Vec<String> =
+                    class.custom_properties.iter().map(|p| code(p)).collect();
+                writeln!(
+                    markdown,
+                    "| {} | {} | {} | {} |",
+                    cell(path),
+                    code(&format!(".{}", name)),
+                    composes.join(", "),
+                    properties.join(", ")
+                )
+                .unwrap();
+            }
+        }
+        markdown.push('\n');
+    }
+
+    markdown
+}
+
+/// Renders the summary as a Markdown section, with a table of the histogram.
+pub fn summary(summary: &Summary) -> String {
+    let ranked = |properties: &[PropertyCount]| -> String {
+        let properties: Vec<String> = properties
+            .iter()
+            .map(|property| format!("{} ({})", code(&property.name), property.usages))
+            .collect();
+        list(&properties)
+    };
+    let single_file: Vec<String> = summary.single_file.iter().map(|p| code(p)).collect();
+
+    let mut markdown = String::from("## Summary\n\n");
+    writeln!(markdown, "- Custom properties: {}", summary.properties).unwrap();
+    writeln!(markdown, "- Usages: {}", summary.usages).unwrap();
+    writeln!(markdown, "- Definitions: {}", summary.definitions).unwrap();
+    writeln!(markdown, "- Most used: {}", ranked(&summary.most_used)).unwrap();
+    writeln!(markdown, "- Least used: {}", ranked(&summary.least_used)).unwrap();
+    writeln!(markdown, "- Used in only one file: {}", list(&single_file)).unwrap();
+    markdown.push_str("\n| Usages | Custom properties |\n| --- | ---: |\n");
+    for bucket in &summary.histogram {
+        writeln!(markdown, "| {} | {} |", bucket.label(), bucket.properties).unwrap();
+    }
+    markdown.push('\n');
+    markdown
+}
+
+/// Renders the problems reading and parsing the inputs as a Markdown table, or nothing when
+/// there were none.
+pub fn diagnostics(diagnostics: &[Diagnostic]) -> String {
+    if diagnostics.is_empty() {
+        return String::new();
+    }
+
+    let mut markdown = String::from("## Diagnostics\n\n");
+    markdown.push_str("| Severity | Message | Location |\n| --- | --- | --- |\n");
+    for diagnostic in diagnostics {
+        let location = match &diagnostic.location {
+            Some(location) => location.to_string(),
+            None => diagnostic.path.clone(),
+        };
+        writeln!(
+            markdown,
+            "| {} | {} | {} |",
+            diagnostic.severity.name(),
+            cell(&diagnostic.message),
+            cell(&location)
+        )
+        .unwrap();
+    }
+    markdown.push('\n');
+    markdown
+}
+
+/// Renders the dead rules and the custom properties only they use as Markdown.
+pub fn dead_rules(report: &DeadSelectorReport) -> String {
+    let mut markdown = String::from("# Dead Selectors\n\n");
+    writeln!(
+        markdown,
+        "Style rules that match no element in the markup: {}\n",
+        report.rules.len()
+    )
+    .unwrap();
+    markdown.push_str("| Selector | Location | Custom properties |\n| --- | --- | --- |\n");
+    for rule in &report.rules {
+        let properties: Vec<String> = rule.custom_properties.iter().map(|p| code(p)).collect();
+        writeln!(
+            markdown,
+            "| {} | {} | {} |",
+            code(&rule.selector),
+            cell(&rule.location.to_string()),
+            properties.join(", ")
+        )
+        .unwrap();
+    }
+
+    markdown.push_str("\n## Custom properties that would become unused\n\n");
+    for property in &report.orphaned_custom_properties {
+        writeln!(markdown, "- {}", code(property)).unwrap();
+    }
+    markdown
+}
+
+// wrap text in a code span that works inside a table cell, using a longer run of backticks
+// when the text contains them
+fn code(text: &str) -> String {
+    let text = one_line(text).replace('|', "\\|");
+    let longest = text
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+// table cells can't span lines, so put a selector's at-rules on the same line as it
+fn one_line(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// escape text for a table cell
+fn cell(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in one_line(text).chars() {
+        if matches!(
+            c,
+            '|' | '\\' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '`'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// the id GitHub gives a heading with the text `text`, ignoring code span backticks
+fn anchor(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c.to_ascii_lowercase()),
+            ' ' => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,322 @@
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// A pattern matched against whole custom property names: a regular expression between
+/// slashes, like `/^--_[a-z]/`, or otherwise a glob, like `--tw-*`.
+#[derive(Clone)]
+pub enum NamePattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    /// Parses a pattern, which is a regular expression if it starts and ends with `/`.
+    pub fn parse(pattern: &str) -> Result<NamePattern, String> {
+        match pattern
+            .strip_prefix('/')
+            .and_then(|pattern| pattern.strip_suffix('/'))
+        {
+            Some(regex) => Regex::new(regex)
+                .map(NamePattern::Regex)
+                .map_err(|error| format!("invalid regular expression '{}': {}", pattern, error)),
+            None => Glob::new(pattern)
+                .map(|glob| NamePattern::Glob(glob.compile_matcher()))
+                .map_err(|error| format!("invalid glob '{}': {}", pattern, error.kind())),
+        }
+    }
+
+    /// Whether the pattern matches the custom property's name. Regular expressions match
+    /// anywhere in the name unless they're anchored.
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(glob) => glob.is_match(name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// A naming convention for custom properties, like `--{namespace}-{category}-{name}`. Each
+/// `{placeholder}` stands for a kebab-case word, or for one of its allowed values when it has
+/// any. A placeholder at the end of the pattern may span several words.
+pub struct NamingPattern {
+    pub pattern: String,
+    pub values: BTreeMap<String, Vec<String>>,
+    segments: Vec<Segment>,
+    regex: Regex,
+}
+
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+impl NamingPattern {
+    /// Parses a naming pattern, checking that every placeholder with allowed values is in it.
+    pub fn new(
+        pattern: &str,
+        values: BTreeMap<String, Vec<String>>,
+    ) -> Result<NamingPattern, String> {
+        let segments = segments(pattern)?;
+        for placeholder in values.keys() {
+            let in_pattern = segments.iter().any(
+                |segment| matches!(segment, Segment::Placeholder(name) if name == placeholder),
+            );
+            if !in_pattern {
+                return Err(format!(
+                    "there's no {{{}}} placeholder in the naming pattern '{}'",
+                    placeholder, pattern
+                ));
+            }
+        }
+
+        let mut regex = String::from("^");
+        for (index, segment) in segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(text) => regex.push_str(&regex::escape(text)),
+                Segment::Placeholder(name) => match values.get(name) {
+                    Some(allowed) => {
+                        let allowed: Vec<String> =
+                            allowed.iter().map(|value| regex::escape(value)).collect();
+                        regex.push_str(&format!("(?:{})", allowed.join("|")));
+                    }
+                    None if index == segments.len() - 1 => {
+                        regex.push_str("[a-z0-9]+(?:-[a-z0-9]+)*")
+                    }
+                    None => regex.push_str("[a-z0-9]+"),
+                },
+            }
+        }
+        regex.push('$');
+
+        Ok(NamingPattern {
+            pattern: pattern.to_string(),
+            values,
+            segments,
+            regex: Regex::new(&regex).map_err(|error| error.to_string())?,
+        })
+    }
+
+    /// Whether the custom property's name follows the convention.
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+
+    /// The pattern along with the values each placeholder allows, like
+    /// `--{namespace}-{name} (namespace: ds or app)`.
+    pub fn describe(&self) -> String {
+        let allowed: Vec<String> = self
+            .values
+            .iter()
+            .map(|(placeholder, values)| format!("{}: {}", placeholder, values.join(" or ")))
+            .collect();
+        match allowed.is_empty() {
+            true => self.pattern.clone(),
+            false => format!("{} ({})", self.pattern, allowed.join(", ")),
+        }
+    }
+
+    /// A name close to `name` that follows the convention, if one can be guessed: the name in
+    /// kebab-case, or with each allowed value of the pattern's first placeholder added in front.
+    pub fn suggestion(&self, name: &str) -> Option<String> {
+        let kebab = kebab_case(name);
+        let mut candidates = vec![kebab.clone()];
+        if let [Segment::Literal(before), Segment::Placeholder(first), Segment::Literal(after), ..] =
+            self.segments.as_slice()
+        {
+            if let (Some(rest), Some(values)) = (kebab.strip_prefix(before), self.values.get(first))
+            {
+                // a name that already starts with one of the values has the wrong shape for some
+                // other reason, which adding another value won't fix
+                let has_value = values
+                    .iter()
+                    .any(|value| rest.starts_with(&format!("{}{}", value, after)));
+                if !has_value {
+                    candidates.extend(
+                        values
+                            .iter()
+                            .map(|value| format!("{}{}{}{}", before, value, after, rest)),
+                    );
+                }
+            }
+        }
+        candidates
+            .into_iter()
+            .find(|candidate| candidate != name && self.is_match(candidate))
+    }
+}
+
+/// Parses a naming pattern given on the command line, checking its placeholders.
+pub fn parse_naming_pattern(pattern: &str) -> Result<String, String> {
+    segments(pattern).map(|_| pattern.to_string())
+}
+
+/// Parses the allowed values of a naming pattern placeholder, like `namespace=ds,app`.
+pub fn parse_naming_values(setting: &str) -> Result<(String, Vec<String>), String> {
+    let (placeholder, values) = setting
+        .split_once('=')
+        .ok_or_else(|| "expected PLACEHOLDER=VALUE[,VALUE...], like namespace=ds".to_string())?;
+    let values: Vec<String> = values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect();
+    if values.is_empty() {
+        return Err(format!("no values given for {{{}}}", placeholder));
+    }
+    Ok((placeholder.to_string(), values))
+}
+
+// split a naming pattern into its literal text and placeholders, which have to be separated
+// by some text for the pattern to be unambiguous
+fn segments(pattern: &str) -> Result<Vec<Segment>, String> {
+    if !pattern.starts_with("--") {
+        return Err(format!(
+            "the naming pattern '{}' must start with --",
+            pattern
+        ));
+    }
+    let mut segments: Vec<Segment> = vec![];
+    let mut rest = pattern;
+    while !rest.is_empty() {
+        match rest.find('{') {
+            Some(0) => {
+                let end = rest.find('}').ok_or_else(|| {
+                    format!("unclosed placeholder in the naming pattern '{}'", pattern)
+                })?;
+                let name = &rest[1..end];
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!(
+                        "invalid placeholder {{{}}} in the naming pattern '{}'",
+                        name, pattern
+                    ));
+                }
+                if let Some(Segment::Placeholder(_)) = segments.last() {
+                    return Err(format!(
+                        "placeholders must be separated by text in the naming pattern '{}'",
+                        pattern
+                    ));
+                }
+                segments.push(Segment::Placeholder(name.to_string()));
+                rest = &rest[end + 1..];
+            }
+            Some(start) => {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+                rest = &rest[start..];
+            }
+            None => {
+                segments.push(Segment::Literal(rest.to_string()));
+                rest = "";
+            }
+        }
+    }
+    Ok(segments)
+}
+
+// convert a custom property name like --dsColor_primary to --ds-color-primary
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::from("--");
+    let mut previous: Option<char> = None;
+    for c in name.trim_start_matches('-').chars() {
+        match c {
+            '_' | '-' | ' ' | '.' => {
+                if !kebab.ends_with('-') {
+                    kebab.push('-');
+                }
+            }
+            c if c.is_uppercase() => {
+                if previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+                    && !kebab.ends_with('-')
+                {
+                    kebab.push('-');
+                }
+                kebab.extend(c.to_lowercase());
+            }
+            c => kebab.push(c),
+        }
+        previous = Some(c);
+    }
+    kebab.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(pattern: &str, values: &[(&str, &[&str])]) -> NamingPattern {
+        let values = values
+            .iter()
+            .map(|(placeholder, values)| {
+                let values = values.iter().map(|value| value.to_string()).collect();
+                (placeholder.to_string(), values)
+            })
+            .collect();
+        NamingPattern::new(pattern, values).unwrap()
+    }
+
+    #[test]
+    fn matches_a_final_placeholder_spanning_several_words() {
+        let naming = naming("--{namespace}-{category}-{name}", &[]);
+        assert!(naming.is_match("--ds-color-primary"));
+        assert!(naming.is_match("--ds-color-brand-primary-hover"));
+        assert!(!naming.is_match("--ds-color"));
+        assert!(!naming.is_match("--ds-color-Primary"));
+    }
+
+    #[test]
+    fn suggests_kebab_case_names() {
+        let categorized = naming("--{namespace}-{category}-{name}", &[("namespace", &["ds"])]);
+        assert_eq!(
+            categorized.suggestion("--dsColorPrimary").as_deref(),
+            Some("--ds-color-primary")
+        );
+        assert_eq!(
+            categorized.suggestion("--ds_color_primary").as_deref(),
+            Some("--ds-color-primary")
+        );
+        assert_eq!(
+            categorized.suggestion("--ds-Space_1").as_deref(),
+            Some("--ds-space-1")
+        );
+
+        // a name that already has a namespace doesn't get another one
+        let namespaced = naming("--{namespace}-{name}", &[("namespace", &["ds", "app"])]);
+        assert_eq!(
+            namespaced.suggestion("--dsColor").as_deref(),
+            Some("--ds-color")
+        );
+        let categorized = naming(
+            "--{namespace}-{category}-{name}",
+            &[("namespace", &["ds", "app"])],
+        );
+        assert_eq!(categorized.suggestion("--dsColor"), None);
+        assert_eq!(categorized.suggestion("--app-x"), None);
+    }
+
+    #[test]
+    fn suggests_adding_an_allowed_namespace() {
+        let naming = naming(
+            "--{namespace}-{category}-{name}",
+            &[("namespace", &["ds", "app"])],
+        );
+        assert!(!naming.is_match("--color-primary"));
+        assert_eq!(
+            naming.suggestion("--color-primary").as_deref(),
+            Some("--ds-color-primary")
+        );
+        assert_eq!(naming.suggestion("--x"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(parse_naming_pattern("--{namespace}{name}").is_err());
+        assert!(parse_naming_pattern("{namespace}-{name}").is_err());
+        assert!(parse_naming_pattern("--{namespace").is_err());
+        assert!(parse_naming_pattern("--{}-x").is_err());
+        assert!(
+            NamingPattern::new("--{namespace}-x", BTreeMap::from([("name".into(), vec![])]))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,268 @@
+// the usages and source snippets the interactive views are built from
+const data = JSON.parse(document.getElementById("css-audit-data").textContent);
+
+// whether there's anything for the filters and views to show
+const hasUsages = data.usages.length > 0 || data.definitions.length > 0;
+
+// what the report is currently showing, changed by the filters and the search
+const state = {
+    view: data.view,
+    path: "*",
+    context: "*",
+    minCount: 1,
+    search: "",
+};
+
+const contextOf = usage => usage.context.join(" ");
+const labelOf = usage => [...usage.context, usage.selector].join("\n");
+const locationOf = location => `${location.path}:${location.line}:${location.column}`;
+const byText = ([a], [b]) => a.localeCompare(b);
+
+// whether a path is the file, or in the directory, picked in the filters
+function matchesPath(path) {
+    if (state.path === "*") {
+        return true;
+    }
+    const [kind, ...rest] = state.path.split(":");
+    const filter = rest.join(":");
+    return kind === "file" ? path === filter : path.startsWith(`${filter}/`);
+}
+
+// set an element's text, keeping line breaks
+function appendLines(element, text) {
+    text.split("\n").forEach((line, index) => {
+        if (index > 0) {
+            element.append(document.createElement("br"));
+        }
+        element.append(line.trim());
+    });
+}
+
+// re-render the usages for the current filters, then hide the sections that don't match the
+// search and let the minimap know
+function refresh() {
+    document.querySelector("css-audit-usages")?.render();
+
+    const search = state.search.trim().toLowerCase();
+    const sections = Array.from(document.querySelectorAll("main section.group"));
+    sections.forEach(section => {
+        const text = Array.from(section.querySelectorAll("h2 .label, li .entry"))
+            .map(element => element.textContent)
+            .join("\n")
+            .toLowerCase();
+        section.hidden = search !== "" && !text.includes(search);
+    });
+    document.querySelector("main .empty").hidden = sections.some(section => !section.hidden);
+
+    document.dispatchEvent(new CustomEvent("css-audit-update"));
+}
+
+class CssAuditUsages extends HTMLElement {
+    connectedCallback() {
+        if (!hasUsages) {
+            return;
+        }
+
+        // the sections rendered into the page are only needed without JavaScript
+        document.querySelectorAll("main section[data-kind='usages']").forEach(section => section.remove());
+        this.render();
+    }
+
+    render() {
+        if (!hasUsages) {
+            return;
+        }
+
+        // the property view lists the selectors reading each custom property, the selector and
+        // file views list what each selector or file reads and defines
+        const byProperty = state.view === "properties";
+        const headingOf = {
+            properties: usage => usage.property,
+            selectors: labelOf,
+            files: usage => usage.location.path,
+        }[state.view];
+        const entries = byProperty
+            ? data.usages.map(usage => [usage, labelOf(usage)])
+            : [
+                ...data.usages.map(usage => [usage, `reads ${usage.property}`]),
+                ...data.definitions.map(definition => [definition, `defines ${definition.property}`]),
+            ];
+        const usages = entries.filter(([usage]) =>
+            matchesPath(usage.location.path) &&
+            (state.context === "*" || contextOf(usage) === state.context)
+        );
+
+        // group the usages under each heading, then by the entry listed under it
+        const groups = new Map();
+        for (const [usage, entry] of usages) {
+            const heading = headingOf(usage);
+            if (!groups.has(heading)) {
+                groups.set(heading, new Map());
+            }
+            const entries = groups.get(heading);
+            if (!entries.has(entry)) {
+                entries.set(entry, new Map());
+            }
+            entries.get(entry).set(locationOf(usage.location), usage.location);
+        }
+
+        const sections = [...groups]
+            .filter(([, entries]) => entries.size >= state.minCount)
+            .sort(byText)
+            .map(([heading, entries], index) => this.section(heading, entries, index));
+        this.replaceChildren(...sections);
+    }
+
+    section(heading, entries, index) {
+        const section = document.createElement("section");
+        section.className = "group";
+
+        const h2 = document.createElement("h2");
+        h2.id = `${state.view}-${index}`;
+        const label = document.createElement("span");
+        label.className = "label";
+        appendLines(label, heading);
+        const count = document.createElement("span");
+        count.className = "count";
+        count.textContent = `(${entries.size})`;
+        h2.append(label, " ", count);
+
+        const ul = document.createElement("ul");
+        for (const [entry, locations] of [...entries].sort(byText)) {
+            const li = document.createElement("li");
+            const text = document.createElement("span");
+            text.className = "entry";
+            appendLines(text, entry);
+            li.append(text);
+            for (const location of locations.values()) {
+                li.append(this.source(location));
+            }
+            ul.append(li);
+        }
+
+        section.append(h2, ul);
+        return section;
+    }
+
+    // the location of a usage, expanding to the lines around it
+    source(location) {
+        const details = document.createElement("details");
+        const summary = document.createElement("summary");
+        summary.textContent = locationOf(location);
+        details.append(summary);
+
+        const snippet = data.snippets[`${location.path}:${location.line}`];
+        if (snippet) {
+            const pre = document.createElement("pre");
+            snippet.lines.forEach((line, index) => {
+                const number = snippet.start + index;
+                const code = document.createElement("code");
+                code.textContent = `${String(number).padStart(5)}  ${line}`;
+                code.classList.toggle("current", number === location.line);
+                pre.append(code);
+            });
+            details.append(pre);
+        }
+
+        return details;
+    }
+}
+
+customElements.define("css-audit-usages", CssAuditUsages);
+
+class CssAuditFilters extends HTMLElement {
+    connectedCallback() {
+        if (!hasUsages) {
+            this.hidden = true;
+            return;
+        }
+
+        this.form = this.querySelector("form");
+        this.form.elements.view.value = state.view;
+        const all = [...data.usages, ...data.definitions];
+        const contexts = new Set(all.map(contextOf));
+        const counts = footprint => `${footprint.usages} usages, ${footprint.definitions} definitions`;
+        this.addOptions(
+            this.form.elements.path,
+            data.directories.map(directory => [`directory:${directory.path}`, `${directory.path}/ (${counts(directory)})`]),
+            "Directories"
+        );
+        this.addOptions(
+            this.form.elements.path,
+            data.files.map(file => [`file:${file.path}`, `${file.path} (${counts(file)})`]),
+            "Files"
+        );
+        this.addOptions(
+            this.form.elements.context,
+            [...contexts].map(context => [context, context === "" ? "Not in an at-rule" : context])
+        );
+
+        this.form.addEventListener("input", this.handleInput.bind(this));
+        this.form.addEventListener("submit", event => event.preventDefault());
+    }
+
+    addOptions(select, options, group) {
+        const parent = group ? document.createElement("optgroup") : select;
+        if (group) {
+            parent.label = group;
+            if (options.length > 0) {
+                select.append(parent);
+            }
+        }
+        for (const [value, label] of options.sort(byText)) {
+            parent.append(new Option(label, value));
+        }
+    }
+
+    handleInput() {
+        const elements = this.form.elements;
+        state.view = elements.view.value;
+        state.path = elements.path.value;
+        state.context = elements.context.value;
+        state.minCount = Number(elements["min-count"].value) || 1;
+        refresh();
+    }
+}
+
+customElements.define("css-audit-filters", CssAuditFilters);
+
+class CssAuditSearch extends HTMLElement {
+    connectedCallback() {
+        this.form = this.querySelector("form");
+        this.input = this.querySelector("input");
+
+        this.form.addEventListener("submit", this.handleSubmit.bind(this));
+        this.input.addEventListener("input", this.handleSubmit.bind(this));
+    }
+
+    // match the search against headings and entries, ignoring case
+    handleSubmit(event) {
+        event.preventDefault();
+        state.search = this.input.value;
+        refresh();
+    }
+}
+
+customElements.define("css-audit-search", CssAuditSearch);
+
+class CssAuditMinimap extends HTMLElement {
+    connectedCallback() {
+        this.render();
+        document.addEventListener("css-audit-update", this.render.bind(this));
+    }
+
+    // link to every section, dimming the ones hidden by the filters or search
+    render() {
+        const links = Array.from(document.querySelectorAll("main section.group")).map(section => {
+            const heading = section.querySelector("h2");
+            const link = document.createElement("a");
+            link.href = `#${heading.id}`;
+            link.append(...heading.querySelector(".label").cloneNode(true).childNodes);
+            link.classList.toggle("dimmed", section.hidden);
+            return link;
+        });
+        this.replaceChildren(...links);
+    }
+}
+
+customElements.define("css-audit-minimap", CssAuditMinimap);
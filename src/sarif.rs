@@ -0,0 +1,136 @@
+use crate::diagnostics::Diagnostic;
+use crate::findings::{Finding, Severity, RULES};
+use crate::summary::Summary;
+use serde_json::{json, Value};
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Renders findings as a SARIF 2.1.0 log with a single run. Problems reading or parsing the
+/// inputs are listed as notifications of the run's invocation, and any summary is included in
+/// the run's property bag.
+pub fn render(
+    findings: &[Finding],
+    diagnostics: &[Diagnostic],
+    summary: Option<&Summary>,
+) -> Value {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+                "defaultConfiguration": {
+                    "level": rule.default_severity.map_or("none", |severity| severity.name()),
+                },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "ruleId": finding.rule.id,
+                "ruleIndex": RULES.iter().position(|rule| rule.id == finding.rule.id),
+                "level": finding.severity.name(),
+                "message": { "text": finding.message },
+                "partialFingerprints": { "cssAuditFinding/v1": fingerprint(finding) },
+                "properties": {
+                    "property": finding.property,
+                    "selector": finding.selector,
+                },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri(&finding.location.path) },
+                        "region": {
+                            "startLine": finding.location.line,
+                            "startColumn": finding.location.column,
+                        },
+                    },
+                }],
+            })
+        })
+        .collect();
+
+    let notifications: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut notification = json!({
+                "level": diagnostic.severity.name(),
+                "message": { "text": diagnostic.message },
+            });
+            let mut physical_location = json!({
+                "artifactLocation": { "uri": uri(&diagnostic.path) },
+            });
+            if let Some(location) = &diagnostic.location {
+                physical_location["region"] =
+                    json!({ "startLine": location.line, "startColumn": location.column });
+            }
+            notification["locations"] = json!([{ "physicalLocation": physical_location }]);
+            notification
+        })
+        .collect();
+
+    let mut run = json!({
+        "tool": {
+            "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": rules,
+            },
+        },
+        "invocations": [{
+            "executionSuccessful": !diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity == Severity::Error),
+            "toolExecutionNotifications": notifications,
+        }],
+        "results": results,
+    });
+    if let Some(summary) = summary {
+        run["properties"] = json!({ "summary": summary });
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    })
+}
+
+// a hash identifying the finding across runs, even when lines move: its rule, the custom
+// property and declaration it's about, its selector and its file. The file is relative to the
+// current directory, however it was given.
+fn fingerprint(finding: &Finding) -> String {
+    let path = Path::new(&finding.location.path);
+    let path = std::env::current_dir()
+        .ok()
+        .and_then(|current_dir| path.strip_prefix(current_dir).ok())
+        .unwrap_or(path);
+    let key = format!(
+        "{}\n{}\n{}\n{}\n{}",
+        finding.rule.id,
+        finding.property,
+        finding.declaration,
+        finding.selector,
+        uri(&path.to_string_lossy())
+    );
+    format!("{:x}", xxh3_64(key.as_bytes()))
+}
+
+// SARIF artifact locations are URI references, so use forward slashes, drop any leading ./
+// and percent-encode anything that isn't allowed in a path
+fn uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,128 @@
+use crate::audit::{Audit, Definition, SelectorUsage, Usage};
+use crate::breakdown::{Breakdown, Footprint};
+use crate::css_modules::{CssModules, ModuleClass};
+use crate::diagnostics::Diagnostic;
+use crate::summary::Summary;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+/// The version of the JSON report, bumped whenever its shape changes in a way that could break
+/// consumers. The matching JSON Schema document is `schema/css-audit.schema.json`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON report. It borrows from the audit when it's written, and owns everything when
+/// it's read back, like by the diff command.
+#[derive(Serialize, Deserialize)]
+pub struct Report<'a> {
+    pub schema_version: u32,
+    pub metadata: Metadata,
+    pub summary: Summary,
+    pub properties: Vec<PropertyReport<'a>>,
+    /// The custom properties each selector reads and defines.
+    pub selectors: Vec<SelectorUsage>,
+    /// How many custom properties each audited file reads and defines.
+    pub files: Vec<Footprint>,
+    /// The totals of the files in each directory containing an audited file.
+    pub directories: Vec<Footprint>,
+    /// The exported classes of each CSS module, keyed by path and then class name.
+    pub css_modules: Cow<'a, BTreeMap<String, BTreeMap<String, ModuleClass>>>,
+    /// Problems reading or parsing the inputs.
+    pub diagnostics: Cow<'a, [Diagnostic]>,
+}
+
+/// Details about the run that produced a report.
+#[derive(Serialize, Deserialize)]
+pub struct Metadata {
+    pub tool: String,
+    pub version: String,
+    /// When the report was generated, as an RFC 3339 timestamp.
+    pub generated_at: String,
+    /// The files that were audited.
+    pub inputs: Vec<String>,
+}
+
+/// Everything known about a single custom property.
+#[derive(Serialize, Deserialize)]
+pub struct PropertyReport<'a> {
+    pub name: String,
+    pub usages: Vec<Cow<'a, Usage>>,
+    pub definitions: Vec<Cow<'a, Definition>>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(
+        audit: &'a Audit,
+        css_modules: &'a CssModules,
+        inputs: &[String],
+        diagnostics: &'a [Diagnostic],
+    ) -> Self {
+        let mut properties: BTreeMap<&str, PropertyReport> = BTreeMap::new();
+        let new_property = |name: &str| PropertyReport {
+            name: name.to_string(),
+            usages: vec![],
+            definitions: vec![],
+        };
+        for usage in &audit.usages {
+            properties
+                .entry(&usage.property)
+                .or_insert_with(|| new_property(&usage.property))
+                .usages
+                .push(Cow::Borrowed(usage));
+        }
+        for definition in &audit.definitions {
+            properties
+                .entry(&definition.property)
+                .or_insert_with(|| new_property(&definition.property))
+                .definitions
+                .push(Cow::Borrowed(definition));
+        }
+
+        let breakdown = Breakdown::new(audit, inputs);
+        Report {
+            schema_version: SCHEMA_VERSION,
+            metadata: Metadata {
+                tool: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                generated_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                inputs: inputs.to_vec(),
+            },
+            summary: Summary::new(audit),
+            properties: properties.into_values().collect(),
+            selectors: audit.selectors(),
+            files: breakdown.files,
+            directories: breakdown.directories,
+            css_modules: Cow::Borrowed(&css_modules.modules),
+            diagnostics: Cow::Borrowed(diagnostics),
+        }
+    }
+
+    /// The report with its own copy of everything it borrows, so it can outlive the audit.
+    pub fn into_owned(self) -> Report<'static> {
+        let owned = |property: PropertyReport| PropertyReport {
+            name: property.name,
+            usages: property
+                .usages
+                .into_iter()
+                .map(|usage| Cow::Owned(usage.into_owned()))
+                .collect(),
+            definitions: property
+                .definitions
+                .into_iter()
+                .map(|definition| Cow::Owned(definition.into_owned()))
+                .collect(),
+        };
+        Report {
+            schema_version: self.schema_version,
+            metadata: self.metadata,
+            summary: self.summary,
+            properties: self.properties.into_iter().map(owned).collect(),
+            selectors: self.selectors,
+            files: self.files,
+            directories: self.directories,
+            css_modules: Cow::Owned(self.css_modules.into_owned()),
+            diagnostics: Cow::Owned(self.diagnostics.into_owned()),
+        }
+    }
+}
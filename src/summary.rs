@@ -0,0 +1,182 @@
+use crate::audit::Audit;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How many custom properties are listed as the most and least used.
+const RANKED_PROPERTIES: usize = 5;
+
+/// The width of the longest bar in the terminal histogram.
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// The ranges of usage counts the histogram groups custom properties into, as an inclusive
+/// minimum and maximum. The last range has no maximum.
+const HISTOGRAM_BUCKETS: &[(usize, Option<usize>)] = &[
+    (0, Some(0)),
+    (1, Some(1)),
+    (2, Some(2)),
+    (3, Some(5)),
+    (6, Some(10)),
+    (11, Some(20)),
+    (21, None),
+];
+
+/// Statistics about the custom properties in the audited stylesheets.
+#[derive(Serialize, Deserialize)]
+pub struct Summary {
+    /// How many custom properties are used or defined.
+    pub properties: usize,
+    pub usages: usize,
+    pub definitions: usize,
+    /// The custom properties with the most usages, most used first.
+    pub most_used: Vec<PropertyCount>,
+    /// The custom properties with the fewest usages, including defined ones that are never
+    /// used, least used first.
+    pub least_used: Vec<PropertyCount>,
+    /// Custom properties that are only used in one file, so may not need to be shared.
+    pub single_file: Vec<String>,
+    /// How many custom properties have each range of usage counts.
+    pub histogram: Vec<Bucket>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PropertyCount {
+    pub name: String,
+    pub usages: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Bucket {
+    /// The fewest usages a custom property in the bucket has.
+    pub min: usize,
+    /// The most usages a custom property in the bucket has, if there's a limit.
+    pub max: Option<usize>,
+    pub properties: usize,
+}
+
+impl Bucket {
+    /// The range of usage counts, like `3-5` or `21+`.
+    pub fn label(&self) -> String {
+        match self.max {
+            Some(max) if max == self.min => max.to_string(),
+            Some(max) => format!("{}-{}", self.min, max),
+            None => format!("{}+", self.min),
+        }
+    }
+}
+
+impl Summary {
+    pub fn new(audit: &Audit) -> Self {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut files: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for definition in &audit.definitions {
+            counts.entry(&definition.property).or_default();
+        }
+        for usage in &audit.usages {
+            *counts.entry(&usage.property).or_default() += 1;
+            files
+                .entry(&usage.property)
+                .or_default()
+                .insert(&usage.location.path);
+        }
+
+        let property_count = |(name, usages): &(&str, usize)| PropertyCount {
+            name: name.to_string(),
+            usages: *usages,
+        };
+        let mut ranked: Vec<(&str, usize)> = counts.clone().into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let most_used = ranked
+            .iter()
+            .filter(|(_, usages)| *usages > 0)
+            .take(RANKED_PROPERTIES)
+            .map(property_count)
+            .collect();
+        ranked.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        let least_used = ranked
+            .iter()
+            .take(RANKED_PROPERTIES)
+            .map(property_count)
+            .collect();
+
+        let histogram = HISTOGRAM_BUCKETS
+            .iter()
+            .map(|&(min, max)| Bucket {
+                min,
+                max,
+                properties: counts
+                    .values()
+                    .filter(|&&usages| usages >= min && max.is_none_or(|max| usages <= max))
+                    .count(),
+            })
+            .collect();
+
+        Summary {
+            properties: counts.len(),
+            usages: audit.usages.len(),
+            definitions: audit.definitions.len(),
+            most_used,
+            least_used,
+            single_file: files
+                .into_iter()
+                .filter(|(_, files)| files.len() == 1)
+                .map(|(property, _)| property.to_string())
+                .collect(),
+            histogram,
+        }
+    }
+
+    /// The summary as lines of text, in the form it's listed in the terminal report.
+    pub fn lines(&self) -> Vec<String> {
+        let ranked = |properties: &[PropertyCount]| -> String {
+            let properties: Vec<String> = properties
+                .iter()
+                .map(|property| format!("{} ({})", property.name, property.usages))
+                .collect();
+            list(&properties)
+        };
+        let mut lines = vec![
+            format!("Custom properties: {}", self.properties),
+            format!("Usages: {}", self.usages),
+            format!("Definitions: {}", self.definitions),
+            format!("Most used: {}", ranked(&self.most_used)),
+            format!("Least used: {}", ranked(&self.least_used)),
+            format!("Used in only one file: {}", list(&self.single_file)),
+            "Custom properties by number of usages:".to_string(),
+        ];
+        let widest = self
+            .histogram
+            .iter()
+            .map(|bucket| bucket.label().len())
+            .max()
+            .unwrap_or(0);
+        let largest = self
+            .histogram
+            .iter()
+            .map(|bucket| bucket.properties)
+            .max()
+            .unwrap_or(0);
+        for bucket in &self.histogram {
+            // scale the bars to fit, but keep a sliver for any bucket that isn't empty
+            let bar = match bucket.properties {
+                0 => 0,
+                properties => (properties * HISTOGRAM_WIDTH / largest).max(1),
+            };
+            lines.push(format!(
+                "  {:>width$}  {} {}",
+                bucket.label(),
+                "#".repeat(bar),
+                bucket.properties,
+                width = widest
+            ));
+        }
+        lines
+    }
+}
+
+/// The items joined with commas, or "none" when there aren't any.
+pub(crate) fn list(items: &[String]) -> String {
+    match items.is_empty() {
+        true => "none".to_string(),
+        false => items.join(", "),
+    }
+}